@@ -0,0 +1,312 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+use task::{Task, TaskList, State};
+use timestamp::{Timestamp, Clock};
+use error::Error;
+use color;
+use tz;
+
+static WEEKDAYS: [&'static str; 7] = [
+    "Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"
+];
+
+/* the agenda covers a year at most */
+static MAX_DAYS: i64 = 366;
+
+static MONTHS: [&'static str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", 
+    "August", "September", "October", "November", "December"
+];
+
+/* the open tasks due in the next days, grouped by day */
+pub struct Agenda<'a> {
+    tasks: &'a TaskList,
+    clock: Clock,
+    days: i64,
+}
+
+/* a month with the number of open deadlines on each day */
+pub struct MonthView<'a> {
+    tasks: &'a TaskList,
+    clock: Clock,
+    year: i64,
+    month: i64,
+}
+
+/* the open tasks due on the local day, by time of day */
+fn due_on(tasks: &TaskList, day: i64) -> Vec<(usize, &Task)> {
+    let tz = tz::default();
+    
+    let mut v: Vec<(usize, &Task)> = tasks.iter().enumerate()
+        .filter(|&(_, x)| !x.completed().valid() && x.deadline().valid())
+        .filter(|&(_, x)| x.deadline().day(&tz) == day)
+        .collect();
+    
+    v.sort_by(|a, b| a.1.deadline().cmp(&b.1.deadline()));
+    v
+}
+
+/* the number of days the agenda covers, e.g. "14" */
+pub fn parse_days(s: &str) -> Result<i64, Error> {
+    match i64::from_str_radix(s, 10) {
+        Ok(x) if x >= 1 && x <= MAX_DAYS => Ok(x),
+        _ => Err(Error::Usage(format!("expected a number of days from 1 \
+                                       to {}", MAX_DAYS))),
+    }
+}
+
+impl<'a> Agenda<'a> {
+    pub fn new(tasks: &'a TaskList, clock: &Clock, days: i64) -> Agenda<'a> {
+        Agenda { tasks: tasks, clock: *clock, days: days }
+    }
+    
+    fn write_task(&self, f: &mut Formatter, i: usize, task: &Task, 
+                  clock: &Clock) -> fmt::Result {
+        let tm = task.deadline().to_tm(&tz::default());
+        
+        let line = format!("  {:3} : {:02}:{:02} -- \"{}\"", 
+                           i + 1, tm.tm_hour, tm.tm_min, task.text());
+        
+        writeln!(f, "{}", color::paint(task.state(clock), &line))
+    }
+}
+
+impl<'a> Display for Agenda<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let clock = Clock::Fixed(self.clock.now());
+        let tz = tz::default();
+        let today = clock.now().day(&tz);
+        
+        let overdue: Vec<(usize, &Task)> = self.tasks.iter().enumerate()
+            .filter(|&(_, x)| !x.completed().valid() && x.deadline().valid())
+            .filter(|&(_, x)| x.deadline().day(&tz) < today)
+            .collect();
+        
+        if !overdue.is_empty() {
+            try!(writeln!(f, "overdue"));
+        }
+        
+        for (i, task) in overdue {
+            try!(self.write_task(f, i, task, &clock));
+        }
+        
+        for day in today..today + self.days {
+            let tm = Timestamp::from_day(day, 0, &tz).to_tm(&tz);
+            
+            let note = match day - today {
+                0 => " (today)",
+                1 => " (tomorrow)",
+                _ => "",
+            };
+            
+            try!(writeln!(f, "{} {}-{:02}-{:02}{}", 
+                          WEEKDAYS[tm.tm_wday as usize], 1900 + tm.tm_year, 
+                          1 + tm.tm_mon, tm.tm_mday, note));
+            
+            for (i, task) in due_on(self.tasks, day) {
+                try!(self.write_task(f, i, task, &clock));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+impl<'a> MonthView<'a> {
+    pub fn new(tasks: &'a TaskList, clock: &Clock, year: i64, month: i64) 
+               -> MonthView<'a> {
+        MonthView { tasks: tasks, clock: *clock, year: year, month: month }
+    }
+    
+    /* the month of the clock in the default time zone */
+    pub fn current(tasks: &'a TaskList, clock: &Clock) -> MonthView<'a> {
+        let tm = clock.now().to_tm(&tz::default());
+        
+        MonthView::new(tasks, clock, 1900 + tm.tm_year as i64, 
+                       1 + tm.tm_mon as i64)
+    }
+    
+    /* e.g. "2024-03" */
+    pub fn from_string(s: &str, tasks: &'a TaskList, clock: &Clock) 
+                       -> Result<MonthView<'a>, Error> {
+        let v: Vec<Option<i64>> = s.splitn(2, '-')
+            .map(|x| i64::from_str_radix(x, 10).ok())
+            .collect();
+        
+        match (v.get(0), v.get(1)) {
+            (Some(&Some(year)), Some(&Some(month))) if month >= 1 
+                && month <= 12 => {
+                Ok(MonthView::new(tasks, clock, year, month))
+            },
+            _ => Err(Error::Usage(format!("invalid month \"{}\" - expected \
+                                           e.g. 2024-03", s))),
+        }
+    }
+    
+    /* "[ 4]" marks today, "(2)" deadlines and "!2" missed deadlines */
+    fn cell(&self, day: i64, today: i64) -> String {
+        let (_, _, mday) = tz::civil_from_days(day);
+        let count = due_on(self.tasks, day).len();
+        
+        let (left, right) = if day == today { ("[", "]") } else { (" ", " ") };
+        
+        let (mark, state) = match count {
+            0 => (String::new(), None),
+            n if day < today => (format!("!{}", n), Some(State::Missed)),
+            n => (format!("({})", n), Some(State::Pending)),
+        };
+        
+        let s = format!("{}{:>2}{}{:<3}", left, mday, right, mark);
+        
+        match state {
+            _ if day == today => color::highlight(&s),
+            Some(x) => color::paint(x, &s),
+            None => s,
+        }
+    }
+}
+
+impl<'a> Display for MonthView<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let tz = tz::default();
+        let today = self.clock.now().day(&tz);
+        
+        let first = tz::days_from_civil(self.year, self.month, 1);
+        let last = first + tz::days_in_month(self.year, self.month);
+        
+        let title = format!("{} {}", MONTHS[self.month as usize - 1], self.year);
+        try!(writeln!(f, "{}", format!("{:^49}", title).trim_right()));
+        
+        /* weeks start on monday */
+        let names: Vec<String> = (1..8)
+            .map(|x| format!(" {:<6}", &WEEKDAYS[x % 7][..2]))
+            .collect();
+        try!(writeln!(f, "{}", names.join("").trim_right()));
+        
+        let offset = (tz::weekday(first) + 6) % 7;
+        let mut line = " ".repeat(7 * offset as usize);
+        
+        for day in first..last {
+            line.push_str(&self.cell(day, today));
+            
+            if (tz::weekday(day) + 6) % 7 == 6 || day + 1 == last {
+                try!(writeln!(f, "{}", line.trim_right()));
+                line.clear();
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList};
+    use task::fixture::clock;
+    use timestamp::{Timestamp, Clock};
+    use tz;
+    use tz::TimeZone;
+    use color;
+    
+    /* 2024-03-13 12:00 UTC, a wednesday */
+    fn setup() -> (TaskList, Clock) {
+        tz::set_default(TimeZone::utc());
+        color::set_default(None);
+        
+        let now = clock(1710331200);
+        let mut tasks = TaskList::new();
+        
+        for &(text, deadline) in &[("late", 1710244800), ("done", 1710331000),
+                                   ("today", 1710345600), ("soon", 1710417600),
+                                   ("later", 1710936000), ("none", 0),
+                                   ("done late", 1710244800)] {
+            let mut task = Task::new(&clock(0));
+            task.set_text(text);
+            
+            if deadline > 0 {
+                task.force_deadline(Timestamp::from_seconds(deadline));
+            }
+            
+            tasks.add(task);
+        }
+        
+        tasks.complete(1, Timestamp::from_seconds(1710330000), &now).unwrap();
+        tasks.complete(6, Timestamp::from_seconds(1710300000), &now).unwrap();
+        
+        (tasks, now)
+    }
+    
+    #[test]
+    fn agenda() {
+        let (tasks, clock) = setup();
+        
+        assert_eq!(format!("{}", Agenda::new(&tasks, &clock, 3)),
+                   "overdue\n\
+                   \x20   1 : 12:00 -- \"late\"\n\
+                    Wed 2024-03-13 (today)\n\
+                   \x20   3 : 16:00 -- \"today\"\n\
+                    Thu 2024-03-14 (tomorrow)\n\
+                   \x20   4 : 12:00 -- \"soon\"\n\
+                    Fri 2024-03-15\n");
+    }
+    
+    #[test]
+    fn days() {
+        assert_eq!(parse_days("14").ok(), Some(14));
+        assert_eq!(parse_days("366").ok(), Some(366));
+        assert!(parse_days("0").is_err());
+        assert!(parse_days("-3").is_err());
+        assert!(parse_days("367").is_err());
+        assert!(parse_days("9223372036854775807").is_err());
+        assert!(parse_days("x").is_err());
+    }
+    
+    #[test]
+    fn month() {
+        let (tasks, clock) = setup();
+        let s = format!("{}", MonthView::current(&tasks, &clock));
+        let lines: Vec<&str> = s.lines().collect();
+        
+        assert_eq!(lines[0].trim(), "March 2024");
+        assert_eq!(lines[1], " Mo     Tu     We     Th     Fr     Sa     Su");
+        assert_eq!(lines[2], 
+                   "                              1      2      3");
+        assert_eq!(lines[4], 
+                   " 11     12 !1 [13](1) 14 (1) 15     16     17");
+        assert_eq!(lines[5].split_whitespace().next(), Some("18"));
+        assert_eq!(lines.len(), 7);
+        
+        assert!(MonthView::from_string("2024-02", &tasks, &clock).is_ok());
+        assert!(MonthView::from_string("2024-13", &tasks, &clock).is_err());
+        assert!(MonthView::from_string("march", &tasks, &clock).is_err());
+    }
+}
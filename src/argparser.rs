@@ -22,107 +22,587 @@
  * SOFTWARE.
  */
 
-use std::ops::Range;
 use std::collections::HashMap;
-use std::cmp::min;
- 
-#[allow(dead_code)]
-pub struct ArgInfo<'a> {
-   name: &'a str,
-   begin: usize,
-   end: usize,
+use std::fmt;
+
+pub struct Opt {
+    long: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+pub struct Command {
+    name: &'static str,
+    help: &'static str,
+    usage: &'static str,
+    min_args: usize,
+    max_args: usize,
+    opts: Vec<Opt>,
+}
+
+pub struct Matches {
+    opts: HashMap<&'static str, Vec<String>>,
+    args: Vec<String>,
+    /* the number of arguments before "--" */
+    escaped: Option<usize>,
+}
+
+/* the global options and the command with its options and arguments */
+pub struct Invocation {
+    pub global: Matches,
+    pub command: Option<&'static str>,
+    pub matches: Matches,
 }
- 
-pub struct ArgParser<'a> {
-   map: HashMap<&'a str, ArgInfo<'a>>,
+
+pub enum Parsed {
+    Help(String),
+    Run(Invocation),
+}
+
+/* a name that stands for a command and some of its arguments */
+struct Alias {
+    name: String,
+    help: String,
+    args: Vec<String>,
 }
- 
-impl<'a> ArgInfo<'a> {
-   pub fn new(name: &'a str) -> ArgInfo<'a> {
-       ArgInfo { 
-           name: name, 
-           begin: 0,
-           end: 0,
-       }
-   }
-   
-   #[allow(dead_code)]
-   pub fn name(&self) -> &'a str {
-       self.name
-   }
-    
-   pub fn has_args(&self) -> bool {
-       self.end > self.begin
-   }
-    
-   pub fn set_begin(&mut self, begin: usize) {
-       self.begin = begin;
-   }
-    
-   pub fn begin(&self) -> usize {
-       self.begin
-   }
-
-   pub fn set_end(&mut self, end: usize) {
-       self.end = end;
-   }
-      
-   pub fn end(&self) -> usize {
-       self.end
-   }
-   
-   pub fn range(&self) -> Range<usize> {
-       self.begin..self.end
-   }
-
-   pub fn is_passed(&self) -> bool {
-       self.begin != 0
-   }
+
+pub struct ArgParser {
+    global: Command,
+    commands: Vec<Command>,
+    aliases: Vec<Alias>,
 }
- 
-impl<'a> ArgParser<'a> {
-   pub fn new() -> ArgParser<'a> {
-       ArgParser { map: HashMap::new() }
-   }
-    
-   pub fn parse(&mut self, args: &'a [String], r: Range<usize>) -> Vec<String> {
-       let mut unknown = vec![];
-       let mut i = r.start;
-       let len = min(args.len(), r.end);
-       
-       while i < len {
-           let arg = args[i].as_ref();
-           
-           self.map.remove(arg).map(|mut x| {
-               i += 1;
-
-               x.set_begin(i);
-               
-               while i < len {
-                    if self.map.contains_key(AsRef::<str>::as_ref(&args[i])) {
-                        break;
-                    }
+
+fn is_option(arg: &str) -> bool {
+    /* "-" and negative numbers are arguments */
+    arg.len() > 1 && arg.starts_with('-') 
+        && !arg[1..].starts_with(|x: char| x.is_digit(10))
+}
+
+impl Command {
+    pub fn new(name: &'static str, help: &'static str) -> Command {
+        Command {
+            name: name,
+            help: help,
+            usage: "",
+            min_args: 0,
+            max_args: 0,
+            opts: vec![Opt { 
+                long: "help", 
+                short: Some('h'), 
+                value: None, 
+                help: "show this help",
+            }],
+        }
+    }
+
+    /* positional arguments, e.g. "<index> <duration>" */
+    pub fn args(mut self, usage: &'static str, min: usize, max: usize) 
+                -> Command {
+        self.usage = usage;
+        self.min_args = min;
+        self.max_args = max;
+        self
+    }
+
+    pub fn flag(mut self, long: &'static str, short: Option<char>, 
+                help: &'static str) -> Command {
+        self.opts.insert(self.opts.len() - 1, Opt { 
+            long: long, 
+            short: short, 
+            value: None, 
+            help: help,
+        });
+        self
+    }
+
+    pub fn option(mut self, long: &'static str, short: Option<char>,
+                  value: &'static str, help: &'static str) -> Command {
+        self.opts.insert(self.opts.len() - 1, Opt { 
+            long: long, 
+            short: short, 
+            value: Some(value), 
+            help: help,
+        });
+        self
+    }
+
+    /* the option and whether it was inherited from "parent" */
+    fn find_opt<'a, P>(&'a self, parent: Option<&'a Command>, pred: P) 
+                       -> Option<(&'a Opt, bool)> 
+        where P: Fn(&Opt) -> bool {
+        match self.opts.iter().find(|x| pred(x)) {
+            Some(x) => Some((x, false)),
+            None => parent.and_then(|p| p.opts.iter().find(|x| pred(x)))
+                .map(|x| (x, true)),
+        }
+    }
+
+    /* 
+     * Parses the arguments of the command. Options and arguments may be
+     * mixed, everything after "--" is an argument. If "first" is set,
+     * parsing stops at the first argument, which is left unconsumed.
+     * Options of "parent" are accepted as well and returned separately,
+     * the options of the command take precedence.
+     */
+    fn parse(&self, args: &[String], first: bool, parent: Option<&Command>) 
+             -> Result<(Matches, Matches, usize), String> {
+        let mut m = Matches { opts: HashMap::new(), args: vec![], escaped: None };
+        let mut inherited = Matches { 
+            opts: HashMap::new(), 
+            args: vec![], 
+            escaped: None,
+        };
+        let mut only_args = false;
+        let mut i = 0;
+
+        while i < args.len() {
+            let arg = &args[i];
+            i += 1;
+
+            if only_args || !is_option(arg) {
+                if first {
+                    return Ok((m, inherited, i - 1));
+                }
+
+                m.args.push(arg.clone());
+                continue;
+            }
+
+            if arg == "--" {
+                only_args = true;
+                m.escaped = Some(m.args.len());
+                continue;
+            }
+
+            if arg.starts_with("--") {
+                let (name, inline) = match arg.find('=') {
+                    Some(x) => (&arg[2..x], Some(arg[x + 1..].to_string())),
+                    None => (&arg[2..], None),
+                };
+
+                let found = self.find_opt(parent, |x| x.long == name);
+                let (opt, inherit) = match found {
+                    Some(x) => x,
+                    None => return Err(format!("unknown option \"--{}\"", name)),
+                };
+
+                let value = match (opt.value, inline) {
+                    (None, None) => String::new(),
+                    (None, Some(_)) => {
+                        return Err(format!("option \"--{}\" takes no value", 
+                                           name));
+                    },
+                    (Some(_), Some(x)) => x,
+                    (Some(_), None) if i < args.len() => {
+                        i += 1;
+                        args[i - 1].clone()
+                    },
+                    (Some(_), None) => {
+                        return Err(format!("option \"--{}\" requires a value", 
+                                           name));
+                    },
+                };
+
+                let target = if inherit { &mut inherited } else { &mut m };
+                target.opts.entry(opt.long).or_insert(vec![]).push(value);
+                continue;
+            }
+
+            /* short options, flags may be combined, e.g. "-fa" */
+            for (j, c) in arg.char_indices().skip(1) {
+                let found = self.find_opt(parent, |x| x.short == Some(c));
+                let (opt, inherit) = match found {
+                    Some(x) => x,
+                    None => return Err(format!("unknown option \"-{}\"", c)),
+                };
+                let target = if inherit { &mut inherited } else { &mut m };
+
+                if opt.value.is_none() {
+                    target.opts.entry(opt.long).or_insert(vec![])
+                        .push(String::new());
+                    continue;
+                }
+
+                let rest = &arg[j + c.len_utf8()..];
+
+                let value = if !rest.is_empty() {
+                    rest.to_string()
+                } else if i < args.len() {
                     i += 1;
-               }
-               
-               x.set_end(i);
-                
-               self.map.insert(arg, x);
-           }).or_else(|| {
-               unknown.push(arg.to_string());
-               i += 1;
-               None
-           });
-       }
-       
-       unknown
-   }
-    
-   pub fn add_opt(&mut self, name: &'a str) {
-       self.map.insert(name, ArgInfo::new(&name));
-   }
-   
-   pub fn get_arginfo(&self, name: &'a str) -> Option<&ArgInfo> {
-       self.map.get(name)
-   }
+                    args[i - 1].clone()
+                } else {
+                    return Err(format!("option \"-{}\" requires a value", c));
+                };
+
+                target.opts.entry(opt.long).or_insert(vec![]).push(value);
+                break;
+            }
+        }
+
+        Ok((m, inherited, i))
+    }
+
+    fn check_args(&self, m: &Matches) -> Result<(), String> {
+        if m.args.len() < self.min_args {
+            Err(format!("missing argument(s) - expected {}", self.usage))
+        } else if m.args.len() > self.max_args {
+            let v: Vec<String> = m.args[self.max_args..].iter()
+                .map(|x| format!("\"{}\"", x))
+                .collect();
+
+            Err(format!("superfluous argument(s) {}", v.join(" ")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn options_help(&self) -> String {
+        let mut s = String::from("\noptions:\n");
+
+        for x in &self.opts {
+            let short = x.short.map_or("    ".to_string(), |c| format!("-{}, ", c));
+            let long = match x.value {
+                Some(v) => format!("--{} {}", x.long, v),
+                None => format!("--{}", x.long),
+            };
+
+            s.push_str(&format!("  {}{:<22} {}\n", short, long, x.help));
+        }
+
+        s
+    }
+
+    pub fn help(&self, prog: &str) -> String {
+        let mut s = format!("usage: {} {} [options] {}\n\n{}\n", 
+                            prog, self.name, self.usage, self.help);
+
+        s.push_str(&self.options_help());
+        s
+    }
+}
+
+impl Matches {
+    pub fn flag(&self, name: &str) -> bool {
+        self.opts.contains_key(name)
+    }
+
+    /* the last value given for the option */
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.opts.get(name).and_then(|x| x.last()).map(|x| x.as_ref())
+    }
+
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.opts.get(name).map_or(vec![], |x| {
+            x.iter().map(|y| y.as_ref()).collect()
+        })
+    }
+
+    /* converts the value of the option with "f" */
+    pub fn value_with<T, E, F>(&self, name: &str, f: F) 
+                               -> Result<Option<T>, String>
+        where F: FnOnce(&str) -> Result<T, E>, E: fmt::Display {
+        match self.value(name) {
+            Some(s) => f(s).map(Some).map_err(|x| {
+                format!("invalid value \"{}\" for --{} - {}", s, name, x)
+            }),
+            None => Ok(None),
+        }
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /* whether the i-th argument was given after "--" */
+    pub fn escaped(&self, i: usize) -> bool {
+        self.escaped.map_or(false, |x| i >= x)
+    }
+}
+
+impl ArgParser {
+    pub fn new(global: Command) -> ArgParser {
+        ArgParser { global: global, commands: vec![], aliases: vec![] }
+    }
+
+    pub fn command(&mut self, cmd: Command) {
+        self.commands.push(cmd);
+    }
+
+    /* 
+     * "args" starts with the name of a command, commands take precedence
+     * over aliases of the same name.
+     */
+    pub fn alias(&mut self, name: &str, help: &str, args: Vec<String>) {
+        self.aliases.push(Alias { 
+            name: name.to_string(), 
+            help: help.to_string(), 
+            args: args,
+        });
+    }
+
+    pub fn help(&self) -> String {
+        let prog = self.global.name;
+        let mut s = format!("usage: {} [options] [command] [arguments]\n\n{}\n", 
+                            prog, self.global.help);
+
+        s.push_str(&self.global.options_help());
+        s.push_str("\ncommands:\n");
+
+        for x in &self.commands {
+            s.push_str(&format!("  {:<12} {}\n", x.name, x.help));
+        }
+
+        let aliases: Vec<&Alias> = self.aliases.iter()
+            .filter(|x| self.find_alias(&x.name).is_some())
+            .collect();
+
+        if !aliases.is_empty() {
+            s.push_str("\nreports:\n");
+        }
+
+        for x in aliases {
+            s.push_str(&format!("  {:<12} {}\n", x.name, x.help));
+        }
+
+        s.push_str(&format!("\nThe options above may also follow the \
+                             command. Run \"{} help <command>\" or \"{} \
+                             <command> --help\" for the options of a \
+                             command.\n", prog, prog));
+        s
+    }
+
+    fn find(&self, name: &str) -> Result<&Command, String> {
+        self.commands.iter().find(|x| x.name == name)
+            .ok_or(format!("unknown command \"{}\" - see \"{} --help\"", 
+                           name, self.global.name))
+    }
+
+    fn find_alias(&self, name: &str) -> Option<&Alias> {
+        if self.commands.iter().any(|x| x.name == name) {
+            return None;
+        }
+
+        self.aliases.iter().find(|x| x.name == name)
+    }
+
+    /* "args" does not include the name of the program */
+    pub fn parse(&self, args: &[String]) -> Result<Parsed, String> {
+        self.parse_args(args, true)
+    }
+
+    fn parse_args(&self, args: &[String], expand: bool) 
+                  -> Result<Parsed, String> {
+        let prog = self.global.name;
+
+        let (mut global, _, i) = try!(self.global.parse(args, true, None)
+            .map_err(|x| format!("{} - see \"{} --help\"", x, prog)));
+
+        if global.flag("help") {
+            return Ok(Parsed::Help(self.help()));
+        }
+
+        if i == args.len() {
+            return Ok(Parsed::Run(Invocation {
+                global: global,
+                command: None,
+                matches: Matches { opts: HashMap::new(), args: vec![], escaped: None },
+            }));
+        }
+
+        if args[i] == "help" {
+            let alias = args.get(i + 1).and_then(|x| self.find_alias(x));
+
+            if let Some(x) = alias {
+                return Ok(Parsed::Help(format!("{}: {}\nruns \"{} {}\"\n", 
+                                               x.name, x.help, prog, 
+                                               x.args.join(" "))));
+            }
+
+            return match args.get(i + 1) {
+                Some(x) => self.find(x).map(|x| Parsed::Help(x.help(prog))),
+                None => Ok(Parsed::Help(self.help())),
+            };
+        }
+
+        /* aliases expand once, they cannot refer to other aliases */
+        if let (true, Some(x)) = (expand, self.find_alias(&args[i])) {
+            let mut v = args[..i].to_vec();
+            v.extend(x.args.iter().cloned());
+            v.extend(args[i + 1..].iter().cloned());
+
+            return self.parse_args(&v, false);
+        }
+
+        let cmd = try!(self.find(&args[i]));
+
+        /* global options may follow the command, e.g. "tap list --all" */
+        let result = cmd.parse(&args[i + 1..], false, Some(&self.global))
+            .and_then(|(m, inherited, _)| {
+                if m.flag("help") {
+                    return Ok((m, inherited));
+                }
+
+                cmd.check_args(&m).map(|_| (m, inherited))
+            });
+
+        let (m, inherited) = try!(result.map_err(|x| {
+            format!("{}: {} - see \"{} {} --help\"", cmd.name, x, prog, cmd.name)
+        }));
+
+        if m.flag("help") {
+            return Ok(Parsed::Help(cmd.help(prog)));
+        }
+
+        for (k, v) in inherited.opts {
+            global.opts.entry(k).or_insert(vec![]).extend(v);
+        }
+
+        Ok(Parsed::Run(Invocation { 
+            global: global, 
+            command: Some(cmd.name), 
+            matches: m,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser() -> ArgParser {
+        let mut parser = ArgParser::new(Command::new("tap", "task planer")
+            .flag("all", Some('a'), "all tasks"));
+
+        parser.command(Command::new("add", "add a task")
+            .args("<text> [time]", 1, 2)
+            .flag("force", Some('f'), "force")
+            .option("wait", Some('w'), "<time>", "wait"));
+        parser.command(Command::new("remove", "remove tasks")
+            .args("<index>...", 0, usize::max_value()));
+        parser.alias("clean", "remove the first task", 
+                     vec!["remove".to_string(), "1".to_string()]);
+        parser.alias("add", "shadowed", vec!["remove".to_string()]);
+        parser.alias("loop", "refers to an alias", vec!["clean".to_string()]);
+
+        parser
+    }
+
+    fn parse(s: &[&str]) -> Result<Parsed, String> {
+        let v: Vec<String> = s.iter().map(|x| x.to_string()).collect();
+
+        parser().parse(&v)
+    }
+
+    fn run(s: &[&str]) -> Invocation {
+        match parse(s) {
+            Ok(Parsed::Run(x)) => x,
+            Ok(Parsed::Help(_)) => panic!("unexpected help"),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let x = run(&[]);
+        assert!(x.command.is_none() && !x.global.flag("all"));
+
+        let x = run(&["-a"]);
+        assert!(x.command.is_none() && x.global.flag("all"));
+
+        let x = run(&["--all", "add", "remove", "1d"]);
+        assert_eq!(x.command, Some("add"));
+        assert_eq!(x.matches.args(), ["remove", "1d"]);
+
+        let x = run(&["remove", "1", "-a"]);
+        assert!(x.global.flag("all") && !x.matches.flag("all"));
+        assert_eq!(x.matches.args(), ["1"]);
+
+        let x = run(&["add", "x", "--all", "--", "--all"]);
+        assert!(x.global.flag("all"));
+        assert_eq!(x.matches.args(), ["x", "--all"]);
+    }
+
+    #[test]
+    fn options() {
+        let x = run(&["add", "x", "--wait", "1d", "-f"]);
+        assert!(x.matches.flag("force"));
+        assert_eq!(x.matches.value("wait"), Some("1d"));
+        assert_eq!(x.matches.args(), ["x"]);
+
+        let x = run(&["add", "--wait=2d", "-fw3d", "x"]);
+        assert_eq!(x.matches.value("wait"), Some("3d"));
+
+        let x = run(&["add", "--", "--force", "-1"]);
+        assert!(!x.matches.flag("force"));
+        assert_eq!(x.matches.args(), ["--force", "-1"]);
+        assert!(x.matches.escaped(0));
+
+        let x = run(&["add", "+tag", "--", "+1"]);
+        assert_eq!(x.matches.args(), ["+tag", "+1"]);
+        assert!(!x.matches.escaped(0));
+        assert!(x.matches.escaped(1));
+
+        let n = x.matches.value_with("wait", |_| Ok::<i32, String>(1));
+        assert_eq!(n, Ok(None));
+
+        let x = run(&["add", "x", "-w", "y"]);
+        let n: Result<Option<i32>, String> = 
+            x.matches.value_with("wait", |_| Err("bad".to_string()));
+        assert_eq!(n, Err("invalid value \"y\" for --wait - bad".to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["list"]).is_err());
+        assert!(parse(&["add"]).is_err());
+        assert!(parse(&["add", "x", "1d", "y"]).is_err());
+        assert!(parse(&["add", "x", "--forc"]).is_err());
+        assert!(parse(&["add", "x", "--force=1"]).is_err());
+        assert!(parse(&["add", "x", "--wait"]).is_err());
+        assert!(parse(&["--force", "add", "x"]).is_err());
+    }
+
+    #[test]
+    fn help() {
+        match parse(&["add", "--help"]) {
+            Ok(Parsed::Help(s)) => {
+                assert!(s.starts_with("usage: tap add [options] <text> [time]"));
+                assert!(s.contains("-w, --wait <time>"));
+            },
+            _ => panic!("expected help"),
+        }
+
+        match parse(&["help"]) {
+            Ok(Parsed::Help(s)) => assert!(s.contains("  remove ")),
+            _ => panic!("expected help"),
+        }
+
+        assert!(parse(&["help", "list"]).is_err());
+    }
+
+    #[test]
+    fn aliases() {
+        let x = run(&["-a", "clean", "2"]);
+        assert!(x.global.flag("all"));
+        assert_eq!(x.command, Some("remove"));
+        assert_eq!(x.matches.args(), ["1", "2"]);
+
+        let x = run(&["add", "x"]);
+        assert_eq!(x.command, Some("add"));
+
+        assert!(parse(&["loop"]).is_err());
+
+        match parse(&["help", "clean"]) {
+            Ok(Parsed::Help(s)) => assert!(s.contains("runs \"tap remove 1\"")),
+            _ => panic!("expected help"),
+        }
+
+        match parse(&["--help"]) {
+            Ok(Parsed::Help(s)) => {
+                assert!(s.contains("  clean ") && !s.contains("shadowed"));
+            },
+            _ => panic!("expected help"),
+        }
+    }
 }
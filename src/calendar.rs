@@ -0,0 +1,355 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::cmp::{min, max};
+
+use timestamp::{Timestamp, Duration};
+use tz;
+
+static WEEKDAYS: [&'static str; 7] = [
+    "sun", "mon", "tue", "wed", "thu", "fri", "sat"
+];
+
+static WEEKDAY_NAMES: [&'static str; 7] = [
+    "sunday", "monday", "tuesday", "wednesday", "thursday", "friday", 
+    "saturday"
+];
+
+#[derive(Clone, Debug)]
+pub struct Calendar {
+    workdays: [bool; 7],
+    holidays: Vec<i64>,
+    begin: i64,
+    end: i64,
+}
+
+thread_local!(static DEFAULT_CALENDAR: RefCell<Rc<Calendar>> =
+    RefCell::new(Rc::new(Calendar::new())));
+
+fn parse_time_of_day(s: &str) -> Result<i64, String> {
+    let v: Vec<&str> = s.trim().split(':').collect();
+
+    if v.len() > 2 {
+        return Err(format!("invalid time of day \"{}\"", s));
+    }
+
+    let mut secs = 0;
+
+    for (i, x) in v.iter().enumerate() {
+        let num = try!(i64::from_str_radix(x, 10)
+            .map_err(|_| format!("invalid time of day \"{}\"", s)));
+        let limit = if i == 0 { 24 } else { 60 };
+
+        if num < 0 || num >= limit {
+            return Err(format!("invalid time of day \"{}\"", s));
+        }
+
+        secs += if i == 0 { num * 3600 } else { num * 60 };
+    }
+
+    Ok(secs)
+}
+
+fn parse_date(s: &str) -> Result<i64, String> {
+    let mut v = vec![];
+
+    for x in s.split('-') {
+        v.push(try!(i64::from_str_radix(x, 10)
+            .map_err(|_| format!("invalid date \"{}\"", s))));
+    }
+
+    if v.len() != 3 || v[1] < 1 || v[1] > 12 || v[2] < 1
+        || v[2] > tz::days_in_month(v[0], v[1]) {
+        return Err(format!("invalid date \"{}\"", s));
+    }
+
+    Ok(tz::days_from_civil(v[0], v[1], v[2]))
+}
+
+/* day since the epoch and seconds since midnight in the default zone */
+fn local_day(ts: &Timestamp) -> (i64, i64) {
+    let tz = tz::default();
+
+    (ts.day(&tz), ts.time_of_day(&tz))
+}
+
+impl Calendar {
+    pub fn new() -> Calendar {
+        Calendar {
+            workdays: [false, true, true, true, true, true, false],
+            holidays: vec![],
+            begin: 9 * 3600,
+            end: 17 * 3600,
+        }
+    }
+
+    /* e.g. "mon,tue,wed,thu,fri" or "monday,tuesday" */
+    pub fn set_workdays(&mut self, s: &str) -> Result<(), String> {
+        let mut workdays = [false; 7];
+
+        for x in s.split(',').map(|x| x.trim().to_lowercase()) {
+            let day = WEEKDAYS.iter().position(|y| x == *y)
+                .or(WEEKDAY_NAMES.iter().position(|y| x == *y));
+
+            match day {
+                Some(i) => workdays[i] = true,
+                None => return Err(format!("invalid weekday \"{}\"", x)),
+            }
+        }
+
+        self.workdays = workdays;
+
+        Ok(())
+    }
+
+    /* e.g. "2024-12-25, 2024-12-26" */
+    pub fn set_holidays(&mut self, s: &str) -> Result<(), String> {
+        let mut holidays = vec![];
+
+        for x in s.split(|x| x == ',' || x == ' ').filter(|x| !x.is_empty()) {
+            holidays.push(try!(parse_date(x)));
+        }
+
+        holidays.sort();
+        self.holidays = holidays;
+
+        Ok(())
+    }
+
+    /* e.g. "09:00-17:00" */
+    pub fn set_work_hours(&mut self, s: &str) -> Result<(), String> {
+        let v: Vec<&str> = s.split('-').collect();
+
+        if v.len() != 2 {
+            return Err(format!("invalid working hours \"{}\"", s));
+        }
+
+        let begin = try!(parse_time_of_day(v[0]));
+        let end = try!(parse_time_of_day(v[1]));
+
+        if begin >= end {
+            return Err(format!("invalid working hours \"{}\"", s));
+        }
+
+        self.begin = begin;
+        self.end = end;
+
+        Ok(())
+    }
+
+    pub fn is_business_day(&self, day: i64) -> bool {
+        self.workdays[tz::weekday(day) as usize]
+            && self.holidays.binary_search(&day).is_err()
+    }
+
+    fn end_of(&self, day: i64) -> Timestamp {
+        Timestamp::from_day(day, self.end, &tz::default())
+    }
+
+    /* end of the working hours of the current or next business day */
+    pub fn end_of_day(&self, now: &Timestamp) -> Timestamp {
+        let (mut day, secs) = local_day(now);
+
+        if !self.is_business_day(day) || secs >= self.end {
+            day += 1;
+
+            while !self.is_business_day(day) {
+                day += 1;
+            }
+        }
+
+        self.end_of(day)
+    }
+
+    /* end of the last business day of the current or next week */
+    pub fn end_of_week(&self, now: &Timestamp) -> Timestamp {
+        let (today, _) = local_day(now);
+
+        /* weeks start on monday */
+        let mut sunday = today + (7 - tz::weekday(today)) % 7;
+
+        loop {
+            let mut day = sunday;
+
+            while day > sunday - 7 {
+                if self.is_business_day(day) && self.end_of(day) > *now {
+                    return self.end_of(day);
+                }
+
+                day -= 1;
+            }
+
+            sunday += 7;
+        }
+    }
+
+    /* working time between two points in time */
+    pub fn working_time(&self, from: &Timestamp, to: &Timestamp) -> Duration {
+        let tz = tz::default();
+        let mut total = Duration::zero();
+        let mut day = from.day(&tz);
+
+        if !to.valid() {
+            return total;
+        }
+
+        while day <= to.day(&tz) {
+            if self.is_business_day(day) {
+                let begin = max(*from, Timestamp::from_day(day, self.begin, &tz));
+                let end = min(*to, Timestamp::from_day(day, self.end, &tz));
+
+                if end > begin {
+                    total = total + (end - begin);
+                }
+            }
+
+            day += 1;
+        }
+
+        total
+    }
+
+    /* end of the working hours n business days from now */
+    pub fn add_business_days(&self, now: &Timestamp, n: i64) -> Timestamp {
+        let (mut day, _) = local_day(now);
+        let mut n = n;
+
+        while n > 0 {
+            day += 1;
+
+            if self.is_business_day(day) {
+                n -= 1;
+            }
+        }
+
+        self.end_of(day)
+    }
+}
+
+pub fn set_default(calendar: Calendar) {
+    DEFAULT_CALENDAR.with(|x| *x.borrow_mut() = Rc::new(calendar));
+}
+
+pub fn default() -> Rc<Calendar> {
+    DEFAULT_CALENDAR.with(|x| x.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tz::TimeZone;
+    use task::fixture::NOW;
+
+    #[test]
+    fn settings() {
+        let mut cal = Calendar::new();
+
+        assert!(cal.set_workdays("mon,Tuesday, wed").is_ok());
+        assert_eq!(cal.workdays, [false, true, true, true, false, false, false]);
+        assert!(cal.set_workdays("monkey").is_err());
+        assert!(cal.set_workdays("sunshine").is_err());
+        assert!(cal.set_workdays("mo").is_err());
+
+        assert!(cal.set_work_hours("08:30-23:59").is_ok());
+        assert_eq!((cal.begin, cal.end), (8 * 3600 + 1800, 23 * 3600 + 3540));
+        assert!(cal.set_work_hours("08:00-24:00").is_err());
+        assert!(cal.set_work_hours("08:60-17:00").is_err());
+        assert!(cal.set_work_hours("99-17").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("2024-03-01"), Ok(19783));
+        assert_eq!(parse_date("1970-1-1"), Ok(0));
+        assert!(parse_date("2024-02-29").is_ok());
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("2024-x-12-25").is_err());
+        assert!(parse_date("2024-12-25-1").is_err());
+        assert!(parse_date("2024-12").is_err());
+        assert!(parse_date("2024--12").is_err());
+        assert!(parse_date("").is_err());
+
+        let mut cal = Calendar::new();
+        assert!(cal.set_holidays("2024-12-25, 2024-x-12-26").is_err());
+        assert!(cal.set_holidays("2024-12-26, 2024-12-25").is_ok());
+        assert_eq!(cal.holidays, [20082, 20083]);
+    }
+
+    fn ts(seconds: i64) -> Timestamp {
+        Timestamp::from_seconds(seconds)
+    }
+
+    fn day(day: i64, hours: i64) -> Timestamp {
+        ts((19783 + day) * 86400 + hours * 3600)
+    }
+
+    #[test]
+    fn deadlines() {
+        tz::set_default(TimeZone::utc());
+
+        let mut cal = Calendar::new();
+
+        assert_eq!(cal.end_of_day(&ts(NOW)), day(0, 17));
+        assert_eq!(cal.end_of_day(&day(0, 17)), day(3, 17));
+        assert_eq!(cal.end_of_day(&day(1, 10)), day(3, 17));
+
+        assert_eq!(cal.end_of_week(&ts(NOW)), day(0, 17));
+        assert_eq!(cal.end_of_week(&day(0, 18)), day(7, 17));
+        assert_eq!(cal.end_of_week(&day(3, 8)), day(7, 17));
+
+        assert_eq!(cal.add_business_days(&ts(NOW), 0), day(0, 17));
+        assert_eq!(cal.add_business_days(&ts(NOW), 1), day(3, 17));
+        assert_eq!(cal.add_business_days(&ts(NOW), 5), day(7, 17));
+
+        assert!(cal.set_holidays("2024-03-04, 2024-03-08").is_ok());
+        assert_eq!(cal.end_of_day(&day(0, 18)), day(4, 17));
+        assert_eq!(cal.end_of_week(&day(0, 18)), day(6, 17));
+        assert_eq!(cal.add_business_days(&ts(NOW), 1), day(4, 17));
+        assert_eq!(cal.add_business_days(&ts(NOW), 4), day(10, 17));
+    }
+
+    #[test]
+    fn working_time() {
+        tz::set_default(TimeZone::utc());
+
+        let mut cal = Calendar::new();
+        let hours = |x| Duration::hours(x).seconds();
+        let time = |cal: &Calendar, from, to| {
+            cal.working_time(&from, &to).seconds()
+        };
+
+        assert_eq!(time(&cal, ts(NOW), day(0, 12)), hours(2));
+        assert_eq!(time(&cal, ts(NOW), day(0, 20)), hours(7));
+        assert_eq!(time(&cal, day(0, 6), day(3, 12)), hours(11));
+        assert_eq!(time(&cal, day(1, 0), day(2, 23)), 0);
+        assert_eq!(time(&cal, day(0, 12), ts(NOW)), 0);
+        assert_eq!(time(&cal, ts(NOW), Timestamp::new()), 0);
+
+        assert!(cal.set_holidays("2024-03-04").is_ok());
+        assert_eq!(time(&cal, day(0, 6), day(4, 12)), hours(11));
+    }
+}
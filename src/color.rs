@@ -0,0 +1,262 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::cell::Cell;
+use std::env;
+
+use task::State;
+use error::Error;
+
+static NAMES: [&'static str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+/* e.g. "bold red", "dim color244" or "none" */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Style {
+    color: Option<u8>,
+    bold: bool,
+    dim: bool,
+    underline: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Theme {
+    pub pending: Style,
+    pub waiting: Style,
+    pub missed: Style,
+    pub completed: Style,
+}
+
+/* no theme means no colors at all */
+thread_local!(static DEFAULT_THEME: Cell<Option<Theme>> = 
+    Cell::new(Some(Theme::new())));
+
+impl ColorMode {
+    pub fn from_string(s: &str) -> Result<ColorMode, Error> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(Error::Usage(format!("invalid color mode \"{}\" - \
+                                           expected auto, always or never", s))),
+        }
+    }
+    
+    /* "auto" colors terminals unless NO_COLOR is set, see no-color.org */
+    pub fn enabled(&self, tty: bool) -> bool {
+        match *self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                tty && env::var("NO_COLOR").map_or(true, |x| x.is_empty())
+            },
+        }
+    }
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Style { color: None, bold: false, dim: false, underline: false }
+    }
+    
+    fn bold(color: u8) -> Style {
+        Style { color: Some(color), bold: true, .. Style::new() }
+    }
+    
+    pub fn from_string(s: &str) -> Result<Style, Error> {
+        let mut style = Style::new();
+        
+        for x in s.split_whitespace() {
+            let color = if x.starts_with("color") {
+                u8::from_str_radix(&x[5..], 10).ok()
+            } else {
+                NAMES.iter().position(|y| *y == x).map(|i| i as u8)
+            };
+            
+            match x {
+                "none" => (),
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "underline" => style.underline = true,
+                _ if color.is_some() => style.color = color,
+                _ => {
+                    return Err(Error::Usage(format!("invalid style \"{}\" - \
+                                                     unknown attribute \"{}\"",
+                                                    s, x)));
+                },
+            }
+        }
+        
+        Ok(style)
+    }
+    
+    /* the escape sequence that switches to the style */
+    pub fn escape(&self) -> String {
+        let mut codes = vec![];
+        
+        if self.bold {
+            codes.push(format!("1"));
+        }
+        
+        if self.dim {
+            codes.push(format!("2"));
+        }
+        
+        if self.underline {
+            codes.push(format!("4"));
+        }
+        
+        match self.color {
+            Some(x) if x < 8 => codes.push(format!("{}", 30 + x)),
+            Some(x) => codes.push(format!("38;5;{}", x)),
+            None => (),
+        }
+        
+        if codes.is_empty() {
+            String::new()
+        } else {
+            format!("\x1B[{}m", codes.join(";"))
+        }
+    }
+    
+    pub fn paint(&self, s: &str) -> String {
+        let escape = self.escape();
+        
+        if escape.is_empty() {
+            s.to_string()
+        } else {
+            format!("{}{}\x1B[0m", escape, s)
+        }
+    }
+}
+
+impl Theme {
+    pub fn new() -> Theme {
+        Theme {
+            pending: Style::bold(3),
+            waiting: Style::bold(4),
+            missed: Style::bold(1),
+            completed: Style::bold(2),
+        }
+    }
+    
+    pub fn style(&self, state: State) -> Style {
+        match state {
+            State::Pending => self.pending,
+            State::Waiting => self.waiting,
+            State::Missed => self.missed,
+            State::Completed => self.completed,
+        }
+    }
+    
+    pub fn set_style(&mut self, state: State, style: Style) {
+        match state {
+            State::Pending => self.pending = style,
+            State::Waiting => self.waiting = style,
+            State::Missed => self.missed = style,
+            State::Completed => self.completed = style,
+        }
+    }
+}
+
+pub fn set_default(theme: Option<Theme>) {
+    DEFAULT_THEME.with(|x| x.set(theme));
+}
+
+pub fn default() -> Option<Theme> {
+    DEFAULT_THEME.with(|x| x.get())
+}
+
+/* reverse video, e.g. for today in the calendar */
+pub fn highlight(s: &str) -> String {
+    match default() {
+        Some(_) => format!("\x1B[7m{}\x1B[0m", s),
+        None => s.to_string(),
+    }
+}
+
+/* colors the text by the state of its task, if colors are enabled */
+pub fn paint(state: State, s: &str) -> String {
+    match default() {
+        Some(theme) => theme.style(state).paint(s),
+        None => s.to_string(),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::State;
+    
+    fn escape(s: &str) -> String {
+        Style::from_string(s).unwrap().escape()
+    }
+    
+    #[test]
+    fn styles() {
+        assert_eq!(escape("bold red"), "\x1B[1;31m");
+        assert_eq!(escape("dim color244"), "\x1B[2;38;5;244m");
+        assert_eq!(escape("underline color3"), "\x1B[4;33m");
+        assert_eq!(escape("none"), "");
+        assert_eq!(Style::from_string("none").unwrap().paint("x"), "x");
+        
+        assert!(Style::from_string("bold purple").is_err());
+        assert!(Style::from_string("color256").is_err());
+    }
+    
+    #[test]
+    fn themes() {
+        let mut theme = Theme::new();
+        
+        assert_eq!(theme.style(State::Missed).paint("x"), "\x1B[1;31mx\x1B[0m");
+        
+        theme.set_style(State::Missed, Style::from_string("dim").unwrap());
+        assert_eq!(theme.style(State::Missed).escape(), "\x1B[2m");
+        
+        set_default(None);
+        assert_eq!(paint(State::Missed, "x"), "x");
+        
+        set_default(Some(theme));
+        assert_eq!(paint(State::Missed, "x"), "\x1B[2mx\x1B[0m");
+    }
+    
+    #[test]
+    fn modes() {
+        assert!(ColorMode::Always.enabled(false));
+        assert!(!ColorMode::Never.enabled(true));
+        assert!(!ColorMode::Auto.enabled(false));
+        assert!(ColorMode::from_string("sometimes").is_err());
+    }
+}
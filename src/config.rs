@@ -23,70 +23,187 @@
  */
 
 use std::fs::{File, OpenOptions, DirBuilder};
-use std::io::{Error, Read, Write, Seek, SeekFrom};
+use std::io::{self, Read, Write, Seek, SeekFrom, ErrorKind};
 use std::path::PathBuf;
+
+use error::Error;
  
 pub struct Config {
     file: File,
+    name: String,
+    lines: Vec<String>,
 }
 
-fn trim(s: &mut String) {
-    let mut i = 0;
+fn split_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
     
-    while s.starts_with(" ") || s.starts_with("\t") || s.starts_with("\n") {
-        i += 1;
+    if line.starts_with("#") {
+        return None;
     }
     
-    s.drain(0..i);
-    
-    while s.ends_with(" ") || s.ends_with("\t") || s.ends_with("\n") {
-        s.pop().unwrap();
-    }
+    line.find('=').map(|i| (line[..i].trim(), line[i + 1..].trim()))
 }
 
 impl Config {
-
-    
     pub fn new(path: &PathBuf) -> Result<Config, Error> {
-        let dir = path.parent().unwrap();
+        let name = path.to_string_lossy().into_owned();
+        let dir = try!(path.parent().ok_or_else(|| {
+            Error::io(&name, io::Error::new(ErrorKind::InvalidInput, 
+                                            "not the path of a file"))
+        }));
         
-        match DirBuilder::new().recursive(true).create(dir) {
-            Ok(_) => {
-                let mut open_opts = OpenOptions::new();
-                
-                open_opts.read(true)
-                    .write(true)
-                    .create(true);
-                
-                let file = try!(open_opts.open(path));
+        try!(DirBuilder::new().recursive(true).create(dir)
+             .map_err(|e| Error::io(&dir.to_string_lossy(), e)));
+        
+        let mut open_opts = OpenOptions::new();
+        
+        open_opts.read(true)
+            .write(true)
+            .create(true);
+        
+        let mut file = try!(open_opts.open(path)
+                            .map_err(|e| Error::io(&name, e)));
+        let mut s = String::new();
+        
+        try!(file.read_to_string(&mut s).map_err(|e| Error::io(&name, e)));
+        
+        let mut lines: Vec<String> = s.lines().map(|x| x.to_string()).collect();
+        
+        /* older versions stored nothing but the path of the task file */
+        for x in lines.iter_mut() {
+            let legacy = {
+                let line = x.trim();
                 
-                Ok(Config { file: file })
+                !line.is_empty() && !line.starts_with("#") && !line.contains('=')
+            };
+            
+            if legacy {
+                *x = format!("file = {}", x.trim());
             }
-            Err(err) => {
-                Err(err)
+        }
+        
+        Ok(Config { file: file, name: name, lines: lines })
+    }
+    
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter()
+            .filter_map(|x| split_line(x))
+            .filter(|&(k, _)| k == key)
+            .map(|(_, v)| v)
+            .last()
+    }
+    
+    /* the keys starting with the prefix, without it, and their values */
+    pub fn entries(&self, prefix: &str) -> Vec<(&str, &str)> {
+        let mut v: Vec<(&str, &str)> = vec![];
+        
+        for (k, x) in self.lines.iter().filter_map(|x| split_line(x)) {
+            if !k.starts_with(prefix) || k.len() == prefix.len() {
+                continue;
             }
+            
+            /* later entries replace earlier ones */
+            v.retain(|&(y, _)| y != &k[prefix.len()..]);
+            v.push((&k[prefix.len()..], x));
         }
+        
+        v
     }
     
-    pub fn task_file(&mut self) -> String {
-        let mut s = String::new();
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let line = format!("{} = {}", key, value.trim());
+        
+        let pos = self.lines.iter()
+            .rposition(|x| split_line(x).map_or(false, |(k, _)| k == key));
         
-        self.file.read_to_string(&mut s).unwrap();
+        match pos {
+            Some(i) => self.lines[i] = line,
+            None => self.lines.push(line),
+        }
+        
+        self.save()
+    }
+    
+    fn save(&mut self) -> Result<(), Error> {
+        let mut s = self.lines.join("\n");
+        s.push('\n');
         
-        trim(&mut s);
+        let file = &mut self.file;
         
-        s
+        file.seek(SeekFrom::Start(0))
+            .and_then(|_| file.set_len(0))
+            .and_then(|_| file.write_all(&s.into_bytes()))
+            .map_err(|e| Error::io(&self.name, e))
+    }
+    
+    pub fn task_file(&self) -> String {
+        self.get("file").unwrap_or("").to_string()
     }
     
     pub fn set_task_file(&mut self, name: &String) -> Result<(), Error> {
-        let mut s = name.clone();
+        self.set("file", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn path(name: &str) -> PathBuf {
+        let mut path = ::std::env::temp_dir();
+        path.push(format!("tap-test-{}-{}", ::std::process::id(), name));
+        path.push("tap.conf");
+        
+        path
+    }
+
+    #[test]
+    fn settings() {
+        let path = path("settings");
+        
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# tasks\ncolor = never \nreport.next = limit:5\n\
+                          report.hot = pri:H\nreport.next = limit:3\n")
+            .unwrap();
+        
+        let mut conf = Config::new(&path).unwrap();
+        
+        assert_eq!(conf.get("color"), Some("never"));
+        assert_eq!(conf.get("# tasks"), None);
+        assert_eq!(conf.get("file"), None);
+        assert_eq!(conf.entries("report."), 
+                   vec![("hot", "pri:H"), ("next", "limit:3")]);
+        
+        conf.set("color", "always").unwrap();
+        conf.set_task_file(&format!("/tmp/tasks.json")).unwrap();
+        
+        let conf = Config::new(&path).unwrap();
+        
+        assert_eq!(conf.get("color"), Some("always"));
+        assert_eq!(conf.task_file(), "/tmp/tasks.json");
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().next(), 
+                   Some("# tasks"));
+        
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn legacy() {
+        let path = path("legacy");
+        
+        /* older versions stored nothing but the path of the task file */
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "/home/user/tasks.json\n").unwrap();
+        
+        let mut conf = Config::new(&path).unwrap();
+        assert_eq!(conf.task_file(), "/home/user/tasks.json");
         
-        trim(&mut s);
+        conf.set("color", "never").unwrap();
         
-        try!(self.file.seek(SeekFrom::Start(0)));
-        try!(self.file.set_len(0));
-        try!(self.file.write_all(&s.into_bytes()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), 
+                   "file = /home/user/tasks.json\ncolor = never\n");
         
-        Ok(())
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
     }
 }
@@ -0,0 +1,242 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fs;
+use std::thread;
+use std::time::{SystemTime, Instant};
+use std::time;
+
+use task::{TaskList, TaskFile};
+use timestamp::{Timestamp, Duration, Clock};
+use reminder;
+use error::Error;
+
+/* seconds between two checks of the task file */
+static POLL_INTERVAL: u64 = 1;
+
+/* tasks whose deadline passed in (from, to] */
+pub fn deadlines(tasks: &TaskList, from: &Timestamp, to: &Timestamp) 
+                 -> Vec<usize> {
+    tasks.iter()
+        .enumerate()
+        .filter(|&(_, x)| !x.completed().valid())
+        .filter(|&(_, x)| x.deadline() > *from && x.deadline() <= *to)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/* the next deadline or reminder after "now" */
+pub fn next_event(tasks: &TaskList, now: &Timestamp) -> Option<Timestamp> {
+    let deadline = tasks.iter()
+        .filter(|x| !x.completed().valid() && x.deadline().valid())
+        .map(|x| x.deadline())
+        .filter(|x| x > now)
+        .min();
+
+    match (deadline, reminder::next(tasks, now)) {
+        (Some(a), Some(b)) => Some(if a < b { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+/* milliseconds from "now" until "ts", at least 0 */
+fn millis_until(ts: &Timestamp, now: &Timestamp) -> u64 {
+    let ms = (ts.seconds() - now.seconds()) * 1000 
+        + (ts.millis() as i64 - now.millis() as i64);
+
+    if ms > 0 { ms as u64 } else { 0 }
+}
+
+/* 
+ * Returns once "next" has been reached or the task file changed. The clock
+ * is read on every poll, so changes of the system time are noticed. A fixed
+ * clock does not move, the time it was fixed to is counted up instead.
+ */
+fn wait(path: &str, next: Option<Timestamp>, clock: &Clock) {
+    let stamp = modified(path);
+    let start = Instant::now();
+
+    loop {
+        let now = match *clock {
+            Clock::Fixed(ts) => {
+                ts + Duration::from_seconds(start.elapsed().as_secs() as i64)
+            },
+            Clock::System => clock.now(),
+        };
+
+        let mut step = POLL_INTERVAL * 1000;
+
+        if let Some(ts) = next {
+            let left = millis_until(&ts, &now);
+
+            if left == 0 {
+                return;
+            }
+
+            step = if left < step { left } else { step };
+        }
+
+        thread::sleep(time::Duration::from_millis(step));
+
+        if modified(path) != stamp {
+            return;
+        }
+    }
+}
+
+/* 
+ * Marks delivered reminders in the task file as it is now, so changes made 
+ * while they were delivered are kept. Tasks are recognized by their 
+ * creation time, deadline and description.
+ */
+fn mark_reminded(path: &str, marks: &[(Timestamp, Timestamp, String, Duration)])
+                 -> Result<(), Error> {
+    let mut taskfile = try!(TaskFile::new(path));
+    let mut tasks = try!(taskfile.load());
+
+    for &(created, deadline, ref text, offset) in marks {
+        for i in 0..tasks.len() {
+            let task = &mut tasks[i];
+
+            if task.created() == created && task.deadline() == deadline 
+                && task.text() == text 
+                && task.pending_reminders().contains(&offset) {
+                task.set_reminded(offset);
+            }
+        }
+    }
+
+    taskfile.save(&tasks)
+}
+
+/*
+ * Delivers reminders and deadlines as they become due. The task file is
+ * reloaded on every change, delivered reminders are marked in it.
+ */
+pub fn run(path: &str, command: Option<&str>, clock: &Clock) 
+           -> Result<(), Error> {
+    let mut last = clock.now();
+
+    loop {
+        let mut taskfile = try!(TaskFile::new(path));
+        let mut tasks = try!(taskfile.load());
+
+        let now = clock.now();
+        let mut marks = vec![];
+
+        for x in reminder::due(&tasks, clock) {
+            let msg = reminder::message(&tasks[x.index], &now);
+
+            match reminder::deliver(&msg, command) {
+                Ok(_) => {
+                    let task = &mut tasks[x.index];
+
+                    marks.push((task.created(), task.deadline(), 
+                                task.text().clone(), x.offset));
+                    task.set_reminded(x.offset);
+                },
+                Err(err) => eprintln!("tap: daemon: {}", err),
+            }
+        }
+
+        for i in deadlines(&tasks, &last, &now) {
+            let msg = reminder::message(&tasks[i], &now);
+
+            if let Err(err) = reminder::deliver(&msg, command) {
+                eprintln!("tap: daemon: {}", err);
+            }
+        }
+
+        if !marks.is_empty() {
+            try!(mark_reminded(path, &marks));
+        }
+
+        last = now;
+
+        wait(path, next_event(&tasks, &now), clock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList};
+    use task::fixture::{clock, task};
+    use timestamp::{Timestamp, Duration};
+
+    #[test]
+    fn planning() {
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 5000));
+        tasks.add(task("b", 3000));
+        tasks.add(Task::new(&clock(0)));
+
+        let ts = |x| Timestamp::from_seconds(x);
+
+        assert_eq!(next_event(&tasks, &ts(1000)), Some(ts(3000)));
+        assert_eq!(next_event(&tasks, &ts(3000)), Some(ts(5000)));
+        assert_eq!(next_event(&tasks, &ts(5000)), None);
+
+        tasks[0].add_reminder(Duration::minutes(50));
+        assert_eq!(next_event(&tasks, &ts(1000)), Some(ts(2000)));
+
+        assert_eq!(deadlines(&tasks, &ts(1000), &ts(3000)), vec![1]);
+        assert_eq!(deadlines(&tasks, &ts(3000), &ts(4000)), vec![]);
+        assert_eq!(deadlines(&tasks, &ts(0), &ts(9000)), vec![0, 1]);
+
+        /* completed after its deadline */
+        tasks[1].set_completed(ts(4000), &Clock::Fixed(ts(4000))).unwrap();
+        assert_eq!(next_event(&tasks, &ts(1000)), Some(ts(2000)));
+        assert_eq!(deadlines(&tasks, &ts(0), &ts(9000)), vec![0]);
+    }
+
+    #[test]
+    fn marks() {
+        let mut path = ::std::env::temp_dir();
+        path.push(format!("tap-test-{}-daemon.json", ::std::process::id()));
+        let name = path.to_string_lossy().into_owned();
+
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 5000));
+        tasks[0].add_reminder(Duration::minutes(50));
+        let mark = (tasks[0].created(), tasks[0].deadline(), 
+                    tasks[0].text().clone(), Duration::minutes(50));
+
+        /* a task added while the reminder was delivered */
+        tasks.add(task("b", 3000));
+        TaskFile::new(&name).unwrap().save(&tasks).unwrap();
+
+        mark_reminded(&name, &[mark]).unwrap();
+
+        let tasks = TaskFile::new(&name).unwrap().load().unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(tasks[0].pending_reminders().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,322 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt;
+use std::i64;
+
+use timestamp::{Timestamp, Clock};
+use tz;
+use tz::TimeZone;
+
+/* the half-open interval [begin, end) */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DateRange {
+    begin: Timestamp,
+    end: Timestamp,
+}
+
+fn parse_number(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.chars().all(|x| x.is_digit(10)) {
+        return None;
+    }
+
+    i64::from_str_radix(s, 10).ok()
+}
+
+/* accepts months outside of 1..12 and moves into the adjacent years */
+fn month_begin(year: i64, month: i64) -> i64 {
+    let m = month - 1;
+    let years = if m >= 0 { m / 12 } else { (m - 11) / 12 };
+
+    tz::days_from_civil(year + years, m - years * 12 + 1, 1)
+}
+
+impl DateRange {
+    pub fn new(begin: Timestamp, end: Timestamp) -> DateRange {
+        DateRange { begin: begin, end: end }
+    }
+
+    pub fn unbounded() -> DateRange {
+        DateRange::new(Timestamp::from_seconds(i64::MIN), Timestamp::new())
+    }
+
+    fn from_days(begin: i64, end: i64, tz: &TimeZone) -> DateRange {
+        DateRange::new(Timestamp::from_day(begin, 0, tz),
+                       Timestamp::from_day(end, 0, tz))
+    }
+
+    /* ranges relative to the current day, e.g. "this-week" */
+    fn from_name(s: &str, clock: &Clock, tz: &TimeZone) -> Option<DateRange> {
+        let today = clock.now().day(tz);
+        let (year, month, _) = tz::civil_from_days(today);
+
+        let (shift, unit) = if s.starts_with("this-") {
+            (0, &s[5..])
+        } else if s.starts_with("last-") {
+            (-1, &s[5..])
+        } else if s.starts_with("next-") {
+            (1, &s[5..])
+        } else {
+            match s {
+                "today" => (0, "day"),
+                "yesterday" => (-1, "day"),
+                "tomorrow" => (1, "day"),
+                _ => return None,
+            }
+        };
+
+        let (begin, end) = match unit {
+            "day" => (today + shift, today + shift + 1),
+            "week" => {
+                /* weeks start on monday */
+                let monday = today - (tz::weekday(today) + 6) % 7 + 7 * shift;
+
+                (monday, monday + 7)
+            },
+            "month" => {
+                (month_begin(year, month + shift),
+                 month_begin(year, month + shift + 1))
+            },
+            "quarter" => {
+                let first = (month - 1) / 3 * 3 + 1 + 3 * shift;
+
+                (month_begin(year, first), month_begin(year, first + 3))
+            },
+            "year" => {
+                (month_begin(year + shift, 1), month_begin(year + shift + 1, 1))
+            },
+            _ => return None,
+        };
+
+        Some(DateRange::from_days(begin, end, tz))
+    }
+
+    /* calendar periods, e.g. "2024", "2024-Q1", "2024-03" or "2024-03-01" */
+    fn from_period(s: &str, tz: &TimeZone) -> Option<DateRange> {
+        let v: Vec<&str> = s.split('-').collect();
+
+        let year = match parse_number(v[0]) {
+            Some(x) if v[0].len() == 4 => x,
+            _ => return None,
+        };
+
+        let (begin, end) = match v.len() {
+            1 => (month_begin(year, 1), month_begin(year + 1, 1)),
+            2 if v[1].starts_with('Q') || v[1].starts_with('q') => {
+                let quarter = match parse_number(&v[1][1..]) {
+                    Some(x) if x >= 1 && x <= 4 => x,
+                    _ => return None,
+                };
+                let first = (quarter - 1) * 3 + 1;
+
+                (month_begin(year, first), month_begin(year, first + 3))
+            },
+            2 => {
+                let month = match parse_number(v[1]) {
+                    Some(x) if x >= 1 && x <= 12 => x,
+                    _ => return None,
+                };
+
+                (month_begin(year, month), month_begin(year, month + 1))
+            },
+            3 => {
+                let (month, day) = match (parse_number(v[1]), parse_number(v[2])) {
+                    (Some(m), Some(d)) => (m, d),
+                    _ => return None,
+                };
+
+                if month < 1 || month > 12 || day < 1
+                    || day > tz::days_in_month(year, month) {
+                    return None;
+                }
+
+                let day = tz::days_from_civil(year, month, day);
+
+                (day, day + 1)
+            },
+            _ => return None,
+        };
+
+        Some(DateRange::from_days(begin, end, tz))
+    }
+
+    /* a single range or the end point of "a..b" */
+    fn from_bound(s: &str, clock: &Clock, tz: &TimeZone)
+                  -> Result<DateRange, String> {
+        let range = DateRange::from_name(s, clock, tz)
+            .or_else(|| DateRange::from_period(s, tz));
+
+        if let Some(range) = range {
+            return Ok(range);
+        }
+
+        let ts = try!(Timestamp::from_string(s, clock)
+            .map_err(|x| format!("invalid date range \"{}\" - {}", s, x)));
+
+        Ok(DateRange::new(ts, ts))
+    }
+
+    /*
+     * Parses named ranges ("today", "this-week", "last-month",
+     * "next-quarter", ...), calendar periods ("2024", "2024-Q1", "2024-03",
+     * "2024-03-01") and spans "a..b" between any of those or points in
+     * time, where either side may be omitted. Spans include the whole
+     * period named by their end.
+     */
+    pub fn from_string(s: &str, clock: &Clock) -> Result<DateRange, String> {
+        let tz = tz::default();
+        let s = s.trim();
+
+        if let Some(i) = s.find("..") {
+            let (a, b) = (s[..i].trim(), s[i + 2..].trim());
+            let mut range = DateRange::unbounded();
+
+            if !a.is_empty() {
+                range.begin = try!(DateRange::from_bound(a, clock, &tz)).begin;
+            }
+
+            if !b.is_empty() {
+                range.end = try!(DateRange::from_bound(b, clock, &tz)).end;
+            }
+
+            if range.begin > range.end {
+                return Err(format!("invalid date range \"{}\" - \
+                                    end lies before the beginning", s));
+            }
+
+            return Ok(range);
+        }
+
+        match DateRange::from_bound(s, clock, &tz) {
+            Ok(ref x) if x.begin == x.end => {
+                Err(format!("invalid date range \"{}\" - \
+                             expected a period or \"a..b\"", s))
+            },
+            result => result,
+        }
+    }
+
+    pub fn begin(&self) -> Timestamp {
+        self.begin
+    }
+
+    pub fn end(&self) -> Timestamp {
+        self.end
+    }
+
+    pub fn contains(&self, ts: &Timestamp) -> bool {
+        ts.valid() && *ts >= self.begin && *ts < self.end
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unbounded = DateRange::unbounded();
+
+        if self.begin != unbounded.begin {
+            try!(write!(f, "{} ", self.begin));
+        }
+
+        try!(write!(f, ".."));
+
+        if self.end != unbounded.end {
+            try!(write!(f, " {}", self.end));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::fixture::{NOW, clock};
+    use timestamp::Timestamp;
+    use tz;
+    use tz::TimeZone;
+
+    fn range(s: &str) -> (i64, i64) {
+        tz::set_default(TimeZone::utc());
+
+        let clock = clock(NOW);
+        let range = DateRange::from_string(s, &clock).unwrap();
+
+        (range.begin().seconds(), range.end().seconds())
+    }
+
+    fn day(year: i64, month: i64, day: i64) -> i64 {
+        tz::days_from_civil(year, month, day) * 86400
+    }
+
+    #[test]
+    fn named_ranges() {
+        assert_eq!(range("today"), (day(2024, 3, 1), day(2024, 3, 2)));
+        assert_eq!(range("yesterday"), (day(2024, 2, 29), day(2024, 3, 1)));
+        assert_eq!(range("this-week"), (day(2024, 2, 26), day(2024, 3, 4)));
+        assert_eq!(range("next-week"), (day(2024, 3, 4), day(2024, 3, 11)));
+        assert_eq!(range("last-month"), (day(2024, 2, 1), day(2024, 3, 1)));
+        assert_eq!(range("this-quarter"), (day(2024, 1, 1), day(2024, 4, 1)));
+        assert_eq!(range("last-year"), (day(2023, 1, 1), day(2024, 1, 1)));
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(range("2024"), (day(2024, 1, 1), day(2025, 1, 1)));
+        assert_eq!(range("2024-Q1"), (day(2024, 1, 1), day(2024, 4, 1)));
+        assert_eq!(range("2024-Q4"), (day(2024, 10, 1), day(2025, 1, 1)));
+        assert_eq!(range("2024-12"), (day(2024, 12, 1), day(2025, 1, 1)));
+        assert_eq!(range("2024-02-29"), (day(2024, 2, 29), day(2024, 3, 1)));
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(range("2024-01-01..2024-03-31"),
+                   (day(2024, 1, 1), day(2024, 4, 1)));
+        assert_eq!(range("2024-01-01 12:00..2024-Q1"),
+                   (day(2024, 1, 1) + 12 * 3600, day(2024, 4, 1)));
+        assert_eq!(range("..7d"), (i64::MIN, NOW + 7 * 86400));
+        assert_eq!(range("this-week.."), (day(2024, 2, 26), i64::MAX));
+    }
+
+    #[test]
+    fn errors() {
+        let clock = clock(NOW);
+
+        assert!(DateRange::from_string("2024-Q5", &clock).is_err());
+        assert!(DateRange::from_string("this-decade", &clock).is_err());
+        assert!(DateRange::from_string("7d", &clock).is_err());
+        assert!(DateRange::from_string("2024-03..2024-01", &clock).is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let r = DateRange::new(Timestamp::from_seconds(10),
+                               Timestamp::from_seconds(20));
+
+        assert!(r.contains(&Timestamp::from_seconds(10)));
+        assert!(r.contains(&Timestamp::from_seconds(19)));
+        assert!(!r.contains(&Timestamp::from_seconds(20)));
+        assert!(!DateRange::unbounded().contains(&Timestamp::new()));
+    }
+}
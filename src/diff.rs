@@ -0,0 +1,93 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Change<'a> {
+    Removed(usize, &'a str),
+    Added(usize, &'a str),
+}
+
+/*
+ * The lines that were removed from "a" and added in "b", along with their
+ * indices, based on the longest common subsequence of both.
+ */
+pub fn diff<'a>(a: &'a [String], b: &'a [String]) -> Vec<Change<'a>> {
+    let (n, m) = (a.len(), b.len());
+
+    /* lcs[i][j] is the length of the lcs of a[i..] and b[j..] */
+    let mut lcs = vec![vec![0; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                lcs[i + 1][j]
+            } else {
+                lcs[i][j + 1]
+            };
+        }
+    }
+
+    let mut v = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            v.push(Change::Removed(i, &a[i]));
+            i += 1;
+        } else {
+            v.push(Change::Added(j, &b[j]));
+            j += 1;
+        }
+    }
+
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.split(' ').filter(|x| !x.is_empty()).map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn changes() {
+        let a = lines("a b c d");
+
+        assert_eq!(diff(&a, &a), vec![]);
+        assert_eq!(diff(&a, &lines("a c d")), vec![Change::Removed(1, "b")]);
+        assert_eq!(diff(&a, &lines("a b c d e")), vec![Change::Added(4, "e")]);
+        assert_eq!(diff(&a, &lines("a x c d")), 
+                   vec![Change::Removed(1, "b"), Change::Added(1, "x")]);
+        assert_eq!(diff(&a, &lines("")), 
+                   vec![Change::Removed(0, "a"), Change::Removed(1, "b"),
+                        Change::Removed(2, "c"), Change::Removed(3, "d")]);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+use task::{Task, TaskList};
+use timestamp::{Timestamp, Duration, Clock};
+use daterange::DateRange;
+use calendar;
+
+pub struct EffortReport<'a> {
+    tasks: &'a TaskList,
+    clock: Clock,
+}
+
+/* deadlines whose estimated work exceeds the working time left until then */
+pub struct Overload {
+    pub deadline: Timestamp,
+    pub work: Duration,
+    pub available: Duration,
+}
+
+impl<'a> EffortReport<'a> {
+    pub fn new(tasks: &'a TaskList, clock: &Clock) -> EffortReport<'a> {
+        EffortReport { tasks: tasks, clock: *clock }
+    }
+
+    pub fn due_in(&self, range: &DateRange) -> Vec<&'a Task> {
+        self.tasks.iter()
+            .filter(|x| !x.completed().valid())
+            .filter(|x| range.contains(&x.deadline()))
+            .collect()
+    }
+
+    pub fn overloads(&self) -> Vec<Overload> {
+        let now = self.clock.now();
+        let calendar = calendar::default();
+
+        let mut open: Vec<&Task> = self.tasks.iter()
+            .filter(|x| !x.completed().valid() && x.deadline().valid())
+            .filter(|x| x.remaining() > Duration::zero())
+            .collect();
+
+        open.sort_by(|a, b| a.deadline().cmp(&b.deadline()));
+
+        let mut result = vec![];
+        let mut work = Duration::zero();
+
+        for (i, task) in open.iter().enumerate() {
+            work = work + task.remaining();
+
+            /* report each deadline only once */
+            if i + 1 < open.len() && open[i + 1].deadline() == task.deadline() {
+                continue;
+            }
+
+            let available = calendar.working_time(&now, &task.deadline());
+
+            if work > available {
+                result.push(Overload {
+                    deadline: task.deadline(),
+                    work: work,
+                    available: available,
+                });
+            }
+        }
+
+        result
+    }
+}
+
+impl<'a> Display for EffortReport<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let clock = Clock::Fixed(self.clock.now());
+        let week = DateRange::from_string("this-week", &clock).unwrap();
+
+        let due = self.due_in(&week);
+        let estimated: Duration = due.iter().filter_map(|x| x.estimate()).sum();
+
+        try!(writeln!(f, "due this week : {} task(s), {:#} estimated",
+                      due.len(), estimated));
+
+        try!(writeln!(f, "\n{:3} : {:>11} : {:>11} : {:>11}",
+                      "", "estimated", "tracked", "difference"));
+
+        let mut total_estimated = Duration::zero();
+        let mut total_tracked = Duration::zero();
+
+        for (i, task) in self.tasks.iter().enumerate() {
+            if task.estimate().is_none() && task.tracked() == Duration::zero() {
+                continue;
+            }
+
+            let estimate = task.estimate().unwrap_or(Duration::zero());
+
+            total_estimated = total_estimated + estimate;
+            total_tracked = total_tracked + task.tracked();
+
+            try!(writeln!(f, "{:3} : {:>#11} : {:>#11} : {:>#11} -- \"{}\"",
+                          i + 1, estimate, task.tracked(),
+                          task.tracked() - estimate, task.text()));
+        }
+
+        try!(writeln!(f, "all : {:>#11} : {:>#11} : {:>#11}",
+                      total_estimated, total_tracked,
+                      total_tracked - total_estimated));
+
+        for x in self.overloads() {
+            try!(writeln!(f, "\nwarning: {:#} of estimated work due by {} but \
+                              only {:#} of working time left",
+                          x.work, x.deadline, x.available));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList};
+    use task::fixture::{NOW, clock};
+    use task::fixture;
+    use timestamp::{Timestamp, Duration};
+    use calendar;
+    use calendar::Calendar;
+    use tz;
+    use tz::TimeZone;
+
+    fn task(deadline: i64, estimate: i64) -> Task {
+        let mut task = fixture::task("x", deadline);
+        task.set_estimate(Duration::hours(estimate));
+
+        task
+    }
+
+    #[test]
+    fn overloads() {
+        tz::set_default(TimeZone::utc());
+        calendar::set_default(Calendar::new());
+
+        /* monday, 2024-03-04 09:00 UTC */
+        let now = NOW + 3 * 86400 - 3600;
+        let clock = clock(now);
+        let tuesday = now + 86400 + 8 * 3600;
+
+        let mut tasks = TaskList::new();
+        tasks.add(task(tuesday, 10));
+        tasks.add(task(tuesday, 4));
+        tasks.add(task(tuesday + 86400, 1));
+
+        let report = EffortReport::new(&tasks, &clock);
+        let overloads = report.overloads();
+
+        /* 16h of working time until tuesday evening, 24h until wednesday */
+        assert_eq!(overloads.len(), 0);
+
+        tasks.add(task(tuesday, 3));
+
+        let report = EffortReport::new(&tasks, &clock);
+        let overloads = report.overloads();
+
+        assert_eq!(overloads.len(), 1);
+        assert_eq!(overloads[0].work, Duration::hours(17));
+        assert_eq!(overloads[0].available, Duration::hours(16));
+
+        /* completed after a deadline that already passed */
+        let mut late = task(now - 3600, 5);
+        late.set_completed(Timestamp::from_seconds(now), &clock).unwrap();
+        tasks.add(late);
+
+        let report = EffortReport::new(&tasks, &clock);
+        let overloads = report.overloads();
+
+        assert_eq!(overloads.len(), 1);
+        assert_eq!(overloads[0].work, Duration::hours(17));
+
+        let week = DateRange::from_string("this-week", &clock).unwrap();
+        assert_eq!(report.due_in(&week).len(), 4);
+    }
+}
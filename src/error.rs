@@ -0,0 +1,127 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::fmt;
+use std::io;
+
+/* 
+ * Exit codes, documented in the README so scripts can rely on them. 
+ * Everything else that goes wrong exits with 1.
+ */
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_IO: i32 = 4;
+pub const EXIT_CORRUPT: i32 = 5;
+
+#[derive(Debug)]
+pub enum Error {
+    /* invalid arguments, times, durations or settings */
+    Usage(String),
+    /* a task that does not exist */
+    NotFound(String),
+    /* reading or writing a file failed */
+    Io(String, io::Error),
+    /* a file exists but cannot be understood */
+    Corrupt(String),
+    /* the user declined to go on */
+    Aborted,
+}
+
+impl Error {
+    pub fn io(path: &str, err: io::Error) -> Error {
+        Error::Io(path.to_string(), err)
+    }
+    
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Usage(_) => EXIT_USAGE,
+            Error::NotFound(_) => EXIT_NOT_FOUND,
+            Error::Io(_, _) => EXIT_IO,
+            Error::Corrupt(_) => EXIT_CORRUPT,
+            Error::Aborted => EXIT_FAILURE,
+        }
+    }
+    
+    /* changes the message, but keeps the kind of error */
+    pub fn map_message<F>(self, f: F) -> Error where F: FnOnce(String) -> String {
+        match self {
+            Error::Usage(s) => Error::Usage(f(s)),
+            Error::NotFound(s) => Error::NotFound(f(s)),
+            Error::Corrupt(s) => Error::Corrupt(f(s)),
+            x => x,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref s) => write!(f, "{}", s),
+            Error::NotFound(ref s) => write!(f, "{}", s),
+            Error::Io(ref path, ref err) => write!(f, "\"{}\": {}", path, err),
+            Error::Corrupt(ref s) => write!(f, "{}", s),
+            Error::Aborted => write!(f, "aborted"),
+        }
+    }
+}
+
+/* plain messages come from parsing the command line */
+impl From<String> for Error {
+    fn from(s: String) -> Error {
+        Error::Usage(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    
+    #[test]
+    fn exit_codes() {
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "gone");
+        
+        assert_eq!(Error::from(format!("x")).exit_code(), EXIT_USAGE);
+        assert_eq!(Error::NotFound(format!("x")).exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(Error::io("a", not_found).exit_code(), EXIT_IO);
+        assert_eq!(Error::Corrupt(format!("x")).exit_code(), EXIT_CORRUPT);
+        assert_eq!(Error::Aborted.exit_code(), EXIT_FAILURE);
+    }
+    
+    #[test]
+    fn messages() {
+        let err = io::Error::new(io::ErrorKind::Other, "disk full");
+        
+        assert_eq!(format!("{}", Error::io("/tmp/todo", err)), 
+                   "\"/tmp/todo\": disk full");
+        assert_eq!(format!("{}", Error::Usage(format!("bad"))), "bad");
+        
+        let err = Error::NotFound(format!("gone"))
+            .map_message(|x| format!("{} - sorry", x));
+        assert_eq!(format!("{}", err), "gone - sorry");
+        assert_eq!(err.exit_code(), EXIT_NOT_FOUND);
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::collections::BTreeMap;
+
+use rustc_serialize::json::Json;
+
+use task::{Task, TaskList};
+use timestamp::{Timestamp, Duration, Clock};
+use error::Error;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Text,
+    /* a single array of tasks */
+    Json,
+    /* one task per line */
+    Ndjson,
+}
+
+impl Format {
+    pub fn from_string(s: &str) -> Result<Format, Error> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(Error::Usage(format!("invalid format \"{}\" - expected \
+                                           text, json or ndjson", s))),
+        }
+    }
+}
+
+fn timestamp(ts: Timestamp) -> Json {
+    if ts.valid() { Json::String(ts.to_iso8601()) } else { Json::Null }
+}
+
+fn duration(d: Duration) -> Json {
+    Json::I64(d.seconds())
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+/* 
+ * The schema is documented in the README, fields may be added but never
+ * renamed or removed. "id" is the index shown by "tap list".
+ */
+pub fn task(id: usize, task: &Task, clock: &Clock) -> Json {
+    let mut obj = BTreeMap::new();
+    
+    let tags = task.tags().iter().map(|x| string(x)).collect();
+    let reminders = task.reminders().into_iter().map(duration).collect();
+    
+    obj.insert(format!("id"), Json::U64(id as u64));
+    obj.insert(format!("description"), string(task.text()));
+    obj.insert(format!("state"), string(&format!("{}", task.state(clock))));
+    obj.insert(format!("created"), timestamp(task.created()));
+    obj.insert(format!("deadline"), timestamp(task.deadline()));
+    obj.insert(format!("completed"), timestamp(task.completed()));
+    obj.insert(format!("scheduled"), 
+               task.scheduled().map_or(Json::Null, timestamp));
+    obj.insert(format!("wait"), task.wait().map_or(Json::Null, timestamp));
+    obj.insert(format!("priority"), 
+               task.priority().map_or(Json::Null, |x| string(&x.to_string())));
+    obj.insert(format!("project"), task.project().map_or(Json::Null, string));
+    obj.insert(format!("tags"), Json::Array(tags));
+    obj.insert(format!("estimate"), task.estimate().map_or(Json::Null, duration));
+    obj.insert(format!("tracked"), duration(task.tracked()));
+    obj.insert(format!("reminders"), Json::Array(reminders));
+    
+    Json::Object(obj)
+}
+
+/* the given tasks in the given order, ends with a newline */
+pub fn tasks(format: Format, tasks: &TaskList, v: &[usize], clock: &Clock) 
+             -> String {
+    let objs: Vec<Json> = v.iter()
+        .map(|&i| task(i + 1, &tasks[i], clock))
+        .collect();
+    
+    match format {
+        Format::Ndjson => {
+            objs.iter().map(|x| format!("{}\n", x)).collect()
+        },
+        _ => format!("{}\n", Json::Array(objs)),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json::Json;
+    use task::{Task, TaskList};
+    use timestamp::{Timestamp, Duration, Clock};
+    
+    fn sample() -> (TaskList, Clock) {
+        let clock = Clock::Fixed(Timestamp::from_seconds(1709280000));
+        let mut tasks = TaskList::new();
+        
+        let mut task = Task::new(&clock);
+        task.set_text("write \"docs\"");
+        task.force_deadline(Timestamp::from_seconds(1709290000));
+        task.add_tag("work");
+        task.track(Duration::hours(1));
+        tasks.add(task);
+        
+        let mut task = Task::new(&clock);
+        task.set_text("b");
+        tasks.add(task);
+        
+        (tasks, clock)
+    }
+    
+    #[test]
+    fn schema() {
+        let (tasks, clock) = sample();
+        let obj = task(1, &tasks[0], &clock);
+        
+        assert_eq!(obj["id"], Json::U64(1));
+        assert_eq!(obj["description"], Json::String(format!("write \"docs\"")));
+        assert_eq!(obj["state"], Json::String(format!("pending")));
+        assert_eq!(obj["created"], 
+                   Json::String(format!("2024-03-01T08:00:00.000Z")));
+        assert_eq!(obj["deadline"], 
+                   Json::String(format!("2024-03-01T10:46:40.000Z")));
+        assert_eq!(obj["completed"], Json::Null);
+        assert_eq!(obj["priority"], Json::Null);
+        assert_eq!(obj["tags"], Json::Array(vec![Json::String(format!("work"))]));
+        assert_eq!(obj["tracked"], Json::I64(3600));
+    }
+    
+    #[test]
+    fn formats() {
+        let (tasks, clock) = sample();
+        
+        let s = super::tasks(Format::Json, &tasks, &[1, 0], &clock);
+        let json = Json::from_str(&s).unwrap();
+        let array = json.as_array().unwrap();
+        
+        assert_eq!(array.len(), 2);
+        assert_eq!(array[0]["id"], Json::U64(2));
+        
+        let s = super::tasks(Format::Ndjson, &tasks, &[0, 1], &clock);
+        let lines: Vec<&str> = s.lines().collect();
+        
+        assert_eq!(lines.len(), 2);
+        assert_eq!(Json::from_str(lines[1]).unwrap()["id"], Json::U64(2));
+        
+        assert_eq!(super::tasks(Format::Json, &tasks, &[], &clock), "[]\n");
+        assert_eq!(super::tasks(Format::Ndjson, &tasks, &[], &clock), "");
+    }
+}
@@ -0,0 +1,532 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use task::{Task, TaskList, State, Priority};
+use timestamp::{Timestamp, Clock};
+use daterange::DateRange;
+use error::Error;
+
+/* saved filters may refer to each other, but not endlessly */
+static MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Field {
+    Due,
+    Created,
+    Completed,
+    Scheduled,
+    Wait,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Compare {
+    Before(Timestamp),
+    After(Timestamp),
+    Within(DateRange),
+    Unset,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Expr {
+    All,
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    State(State),
+    Tag(String),
+    Project(Option<String>),
+    Priority(Option<Priority>),
+    Text(String),
+    Date(Field, Compare),
+}
+
+/* 
+ * A filter expression such as 
+ * "status:pending and (due.before:eow or +urgent) and not project:ops".
+ * Terms next to each other are implicitly combined with "and", "@name" 
+ * refers to a saved filter.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Filter {
+    expr: Expr,
+}
+
+struct Parser<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    clock: &'a Clock,
+    saved: &'a Fn(&str) -> Option<String>,
+    depth: usize,
+}
+
+fn tokenize(s: &str) -> Result<Vec<String>, Error> {
+    let mut v = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        if quoted {
+            if c == '"' {
+                quoted = false;
+            } else {
+                token.push(c);
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' => quoted = true,
+            '(' | ')' => {
+                if !token.is_empty() {
+                    v.push(token.clone());
+                    token.clear();
+                }
+
+                v.push(c.to_string());
+            },
+            _ if c.is_whitespace() => {
+                if !token.is_empty() {
+                    v.push(token.clone());
+                    token.clear();
+                }
+            },
+            _ => token.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(Error::Usage(format!("missing closing quote in \"{}\"", 
+                                        s)));
+    }
+
+    if !token.is_empty() {
+        v.push(token);
+    }
+
+    Ok(v)
+}
+
+fn parse_date(s: &str, clock: &Clock) -> Result<DateRange, Error> {
+    DateRange::from_string(s, clock).or_else(|_| {
+        Timestamp::from_string(s, clock).map(|x| DateRange::new(x, x))
+    })
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|x| x.as_ref())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).cloned()
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut expr = try!(self.and());
+
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(try!(self.and())));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut expr = try!(self.unary());
+
+        loop {
+            match self.peek() {
+                Some("and") => self.pos += 1,
+                Some("or") | Some(")") | None => return Ok(expr),
+                _ => (),
+            }
+
+            expr = Expr::And(Box::new(expr), Box::new(try!(self.unary())));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        let token = match self.next() {
+            Some(x) => x,
+            None => {
+                return Err(Error::Usage(format!("unexpected end of the \
+                                                 filter")));
+            },
+        };
+
+        match token.as_ref() {
+            "not" => Ok(Expr::Not(Box::new(try!(self.unary())))),
+            "(" => {
+                let expr = try!(self.or());
+
+                match self.next() {
+                    Some(ref x) if x == ")" => Ok(expr),
+                    _ => Err(Error::Usage(format!("missing \")\""))),
+                }
+            },
+            ")" | "and" | "or" => {
+                Err(Error::Usage(format!("unexpected \"{}\"", token)))
+            },
+            _ => self.term(&token),
+        }
+    }
+
+    fn saved(&self, name: &str) -> Result<Expr, Error> {
+        let s = match (self.saved)(name) {
+            Some(x) => x,
+            None => {
+                return Err(Error::Usage(format!("unknown saved filter \"@{}\"", 
+                                                name)));
+            },
+        };
+
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::Usage(format!("saved filter \"@{}\" refers to \
+                                             itself", name)));
+        }
+
+        Filter::parse(&s, self.clock, self.saved, self.depth + 1)
+            .map(|x| x.expr)
+    }
+
+    fn term(&self, token: &str) -> Result<Expr, Error> {
+        if token.starts_with('+') && token.len() > 1 {
+            return Ok(Expr::Tag(token[1..].to_string()));
+        }
+
+        if token.starts_with('-') && token.len() > 1 {
+            return Ok(Expr::Not(Box::new(Expr::Tag(token[1..].to_string()))));
+        }
+
+        if token.starts_with('@') && token.len() > 1 {
+            return self.saved(&token[1..]);
+        }
+
+        let (key, value) = match token.find(':') {
+            Some(i) => (&token[..i], &token[i + 1..]),
+            None => return Ok(Expr::Text(token.to_lowercase())),
+        };
+
+        let (key, modifier) = match key.find('.') {
+            Some(i) => (&key[..i], &key[i + 1..]),
+            None => (key, ""),
+        };
+
+        let field = match key {
+            "due" | "deadline" => Some(Field::Due),
+            "created" => Some(Field::Created),
+            "completed" => Some(Field::Completed),
+            "scheduled" => Some(Field::Scheduled),
+            "wait" => Some(Field::Wait),
+            _ => None,
+        };
+
+        if let Some(field) = field {
+            if value == "none" && modifier.is_empty() {
+                return Ok(Expr::Date(field, Compare::Unset));
+            }
+
+            let range = try!(parse_date(value, self.clock));
+
+            let cmp = match modifier {
+                "" | "is" => Compare::Within(range),
+                "before" => Compare::Before(range.begin()),
+                "after" => Compare::After(range.end()),
+                _ => {
+                    return Err(Error::Usage(format!("unknown modifier \"{}\" \
+                                                     in \"{}\" - expected \
+                                                     before, after or is", 
+                                                    modifier, token)));
+                },
+            };
+
+            return Ok(Expr::Date(field, cmp));
+        }
+
+        if !modifier.is_empty() {
+            return Err(Error::Usage(format!("unknown modifier \"{}\" in \"{}\"", 
+                                            modifier, token)));
+        }
+
+        match key {
+            "status" | "state" => {
+                State::from_string(value).map(Expr::State)
+            },
+            "project" if value.is_empty() || value == "none" => {
+                Ok(Expr::Project(None))
+            },
+            "project" => Ok(Expr::Project(Some(value.to_string()))),
+            "pri" | "priority" if value.is_empty() || value == "none" => {
+                Ok(Expr::Priority(None))
+            },
+            "pri" | "priority" => {
+                Priority::from_string(value).map(|x| Expr::Priority(Some(x)))
+            },
+            "text" | "description" => Ok(Expr::Text(value.to_lowercase())),
+            _ => Err(Error::Usage(format!("unknown attribute \"{}\"", key))),
+        }
+    }
+}
+
+impl Field {
+    fn get(&self, task: &Task) -> Option<Timestamp> {
+        let ts = match *self {
+            Field::Due => task.deadline(),
+            Field::Created => task.created(),
+            Field::Completed => task.completed(),
+            Field::Scheduled => return task.scheduled(),
+            Field::Wait => return task.wait(),
+        };
+
+        if ts.valid() { Some(ts) } else { None }
+    }
+}
+
+impl Expr {
+    fn matches(&self, task: &Task, clock: &Clock) -> bool {
+        match *self {
+            Expr::All => true,
+            Expr::And(ref a, ref b) => {
+                a.matches(task, clock) && b.matches(task, clock)
+            },
+            Expr::Or(ref a, ref b) => {
+                a.matches(task, clock) || b.matches(task, clock)
+            },
+            Expr::Not(ref x) => !x.matches(task, clock),
+            Expr::State(x) => task.state(clock) == x,
+            Expr::Tag(ref x) => task.has_tag(x),
+            /* "project:ops" also matches "ops.infra" */
+            Expr::Project(ref x) => match (x, task.project()) {
+                (&None, None) => true,
+                (&Some(ref a), Some(b)) => {
+                    b == a || b.starts_with(&format!("{}.", a))
+                },
+                _ => false,
+            },
+            Expr::Priority(x) => task.priority() == x,
+            Expr::Text(ref x) => task.text().to_lowercase().contains(x),
+            Expr::Date(field, cmp) => match (cmp, field.get(task)) {
+                (Compare::Unset, ts) => ts.is_none(),
+                (_, None) => false,
+                (Compare::Before(x), Some(ts)) => ts < x,
+                (Compare::After(x), Some(ts)) => ts >= x,
+                (Compare::Within(x), Some(ts)) if x.begin() == x.end() => {
+                    ts == x.begin()
+                },
+                (Compare::Within(x), Some(ts)) => x.contains(&ts),
+            },
+        }
+    }
+}
+
+impl Filter {
+    /* matches every task */
+    pub fn all() -> Filter {
+        Filter { expr: Expr::All }
+    }
+
+    fn parse(s: &str, clock: &Clock, saved: &Fn(&str) -> Option<String>,
+             depth: usize) -> Result<Filter, Error> {
+        let mut parser = Parser {
+            tokens: try!(tokenize(s)),
+            pos: 0,
+            clock: clock,
+            saved: saved,
+            depth: depth,
+        };
+
+        if parser.tokens.is_empty() {
+            return Ok(Filter::all());
+        }
+
+        let expr = try!(parser.or());
+
+        if let Some(x) = parser.peek() {
+            return Err(Error::Usage(format!("unexpected \"{}\"", x)));
+        }
+
+        Ok(Filter { expr: expr })
+    }
+
+    /* "saved" looks up the saved filters referred to by "@name" */
+    pub fn from_string(s: &str, clock: &Clock, 
+                       saved: &Fn(&str) -> Option<String>) 
+                       -> Result<Filter, Error> {
+        Filter::parse(s, clock, saved, 0)
+            .map_err(|x| {
+                x.map_message(|y| format!("invalid filter \"{}\" - {}", s, y))
+            })
+    }
+
+    pub fn matches(&self, task: &Task, clock: &Clock) -> bool {
+        self.expr.matches(task, clock)
+    }
+
+    /* whether the filter selects tasks by their state */
+    pub fn has_state(&self) -> bool {
+        fn walk(expr: &Expr) -> bool {
+            match *expr {
+                Expr::And(ref a, ref b) | Expr::Or(ref a, ref b) => {
+                    walk(a) || walk(b)
+                },
+                Expr::Not(ref x) => walk(x),
+                Expr::State(_) => true,
+                _ => false,
+            }
+        }
+
+        walk(&self.expr)
+    }
+
+    /* indices of the matching tasks */
+    pub fn select(&self, tasks: &TaskList, clock: &Clock) -> Vec<usize> {
+        let clock = clock.frozen();
+
+        (0..tasks.len()).filter(|&i| self.matches(&tasks[i], &clock)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList, Priority};
+    use task::fixture::{NOW, clock};
+    use timestamp::Timestamp;
+    use tz;
+    use tz::TimeZone;
+
+    fn tasks() -> TaskList {
+        let clock = clock(NOW - 86400);
+        let mut tasks = TaskList::new();
+
+        let mut a = Task::new(&clock);
+        a.set_text("Deploy the service");
+        a.force_deadline(Timestamp::from_seconds(NOW + 3600));
+        a.set_project("ops.infra");
+
+        let mut b = Task::new(&clock);
+        b.set_text("Write the report");
+        b.force_deadline(Timestamp::from_seconds(NOW + 14 * 86400));
+        b.add_tag("urgent");
+        b.set_priority(Priority::High);
+
+        let mut c = Task::new(&clock);
+        c.set_text("Read the paper");
+
+        let mut d = Task::new(&clock);
+        d.set_text("Pay the rent");
+        d.force_deadline(Timestamp::from_seconds(NOW - 3600));
+
+        tasks.add(a);
+        tasks.add(b);
+        tasks.add(c);
+        tasks.add(d);
+
+        tasks
+    }
+
+    fn saved(name: &str) -> Option<String> {
+        match name {
+            "work" => Some("+urgent or project:ops".to_string()),
+            "loop" => Some("@loop".to_string()),
+            _ => None,
+        }
+    }
+
+    fn select(s: &str) -> Vec<usize> {
+        tz::set_default(TimeZone::utc());
+
+        let clock = clock(NOW);
+        let filter = Filter::from_string(s, &clock, &saved).unwrap();
+
+        filter.select(&tasks(), &clock)
+    }
+
+    fn error(s: &str) -> bool {
+        let clock = clock(NOW);
+
+        Filter::from_string(s, &clock, &saved).is_err()
+    }
+
+    #[test]
+    fn terms() {
+        assert_eq!(select(""), vec![0, 1, 2, 3]);
+        assert_eq!(select("status:pending"), vec![0, 1, 2]);
+        assert_eq!(select("status:missed"), vec![3]);
+        assert_eq!(select("+urgent"), vec![1]);
+        assert_eq!(select("-urgent"), vec![0, 2, 3]);
+        assert_eq!(select("project:ops"), vec![0]);
+        assert_eq!(select("project:op"), vec![]);
+        assert_eq!(select("pri:H"), vec![1]);
+        assert_eq!(select("report"), vec![1]);
+        assert_eq!(select("text:\"THE PAPER\""), vec![2]);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(select("due:today"), vec![0, 3]);
+        assert_eq!(select("due.before:eow"), vec![0, 3]);
+        assert_eq!(select("due.after:this-week"), vec![1]);
+        assert_eq!(select("due:none"), vec![2]);
+        assert_eq!(select("created.before:today"), vec![0, 1, 2, 3]);
+        assert_eq!(select("completed:none"), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(select("status:pending and (due.before:eow or +urgent) \
+                           and not project:ops"), vec![1]);
+        assert_eq!(select("status:pending due:none"), vec![2]);
+        assert_eq!(select("not (status:pending or status:missed)"), vec![]);
+        assert_eq!(select("the and not deploy or pri:H"), vec![1, 2, 3]);
+        assert_eq!(select("@work and status:pending"), vec![0, 1]);
+    }
+
+    #[test]
+    fn states() {
+        let clock = clock(NOW);
+        let filter = |s| Filter::from_string(s, &clock, &saved).unwrap();
+
+        assert!(!filter("+urgent or due:today").has_state());
+        assert!(filter("+urgent or not status:completed").has_state());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(error("status:done"));
+        assert!(error("due.since:today"));
+        assert!(error("due:someday"));
+        assert!(error("size:big"));
+        assert!(error("(+urgent"));
+        assert!(error("+urgent)"));
+        assert!(error("+urgent and"));
+        assert!(error("not"));
+        assert!(error("text:\"x"));
+        assert!(error("@unknown"));
+        assert!(error("@loop"));
+    }
+}
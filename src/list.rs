@@ -0,0 +1,264 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::cmp::Ordering;
+
+use task::{Task, TaskList, State, Priority};
+use timestamp::Clock;
+use daterange::DateRange;
+use filter::Filter;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SortKey {
+    Deadline,
+    Created,
+    Priority,
+}
+
+/* which tasks "tap list" shows and in what order */
+pub struct Listing {
+    pub filter: Filter,
+    pub states: Vec<State>,
+    pub all: bool,
+    pub due: Option<DateRange>,
+    pub text: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    pub priority: Option<Priority>,
+    pub sort: Vec<SortKey>,
+    pub limit: Option<usize>,
+}
+
+impl SortKey {
+    /* e.g. "due,priority" */
+    pub fn from_string(s: &str) -> Result<Vec<SortKey>, String> {
+        let mut v = vec![];
+
+        for x in s.split(',').map(|x| x.trim()) {
+            v.push(match x {
+                "due" | "deadline" => SortKey::Deadline,
+                "created" => SortKey::Created,
+                "priority" => SortKey::Priority,
+                _ => return Err(format!("invalid sort key \"{}\" - expected \
+                                         due, created or priority", x)),
+            });
+        }
+
+        Ok(v)
+    }
+
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        match *self {
+            SortKey::Deadline => a.deadline().cmp(&b.deadline()),
+            SortKey::Created => a.created().cmp(&b.created()),
+            /* highest priority first, tasks without a priority last */
+            SortKey::Priority => b.priority().cmp(&a.priority()),
+        }
+    }
+}
+
+impl Listing {
+    pub fn new() -> Listing {
+        Listing {
+            filter: Filter::all(),
+            states: vec![],
+            all: false,
+            due: None,
+            text: None,
+            project: None,
+            tags: vec![],
+            priority: None,
+            sort: vec![],
+            limit: None,
+        }
+    }
+
+    /* without explicit states, waiting tasks are only shown if "all" is set */
+    pub fn hides_waiting(&self) -> bool {
+        self.states.is_empty() && !self.all && !self.filter.has_state()
+    }
+
+    pub fn matches(&self, task: &Task, clock: &Clock) -> bool {
+        let state = task.state(clock);
+
+        if self.hides_waiting() && state == State::Waiting {
+            return false;
+        }
+
+        if !self.states.is_empty() && !self.states.contains(&state) {
+            return false;
+        }
+
+        if !self.filter.matches(task, clock) {
+            return false;
+        }
+
+        if let Some(ref x) = self.due {
+            if !x.contains(&task.deadline()) {
+                return false;
+            }
+        }
+
+        if let Some(ref x) = self.text {
+            if !task.text().to_lowercase().contains(&x.to_lowercase()) {
+                return false;
+            }
+        }
+
+        if let Some(ref x) = self.project {
+            if task.project() != Some(x.as_ref()) {
+                return false;
+            }
+        }
+
+        if self.priority.is_some() && task.priority() != self.priority {
+            return false;
+        }
+
+        self.tags.iter().all(|x| task.has_tag(x))
+    }
+
+    /* indices of the matching tasks in listing order */
+    pub fn select(&self, tasks: &TaskList, clock: &Clock) -> Vec<usize> {
+        let clock = clock.frozen();
+
+        let mut v: Vec<usize> = (0..tasks.len())
+            .filter(|&i| self.matches(&tasks[i], &clock))
+            .collect();
+
+        v.sort_by(|&a, &b| {
+            self.sort.iter()
+                .map(|x| x.compare(&tasks[a], &tasks[b]))
+                .find(|&x| x != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        if let Some(n) = self.limit {
+            v.truncate(n);
+        }
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList, State, Priority};
+    use task::fixture::clock;
+    use timestamp::Timestamp;
+    use daterange::DateRange;
+    use filter::Filter;
+
+    fn tasks() -> TaskList {
+        let mut tasks = TaskList::new();
+
+        let mut a = Task::new(&clock(100));
+        a.set_text("Write the report");
+        a.force_deadline(Timestamp::from_seconds(5000));
+        a.set_priority(Priority::Low);
+        a.add_tag("work");
+
+        let mut b = Task::new(&clock(200));
+        b.set_text("Call mom");
+        b.force_deadline(Timestamp::from_seconds(1000));
+
+        let mut c = Task::new(&clock(50));
+        c.set_text("Review the report");
+        c.force_deadline(Timestamp::from_seconds(3000));
+        c.set_priority(Priority::High);
+        c.set_project("ops");
+        c.add_tag("work");
+        c.set_wait(Timestamp::from_seconds(2500));
+
+        tasks.add(a);
+        tasks.add(b);
+        tasks.add(c);
+
+        tasks
+    }
+
+    #[test]
+    fn filters() {
+        let tasks = tasks();
+        let clock = clock(2000);
+        let mut listing = Listing::new();
+
+        assert_eq!(listing.select(&tasks, &clock), vec![0, 1]);
+
+        listing.all = true;
+        assert_eq!(listing.select(&tasks, &clock), vec![0, 1, 2]);
+
+        listing.states = vec![State::Missed, State::Waiting];
+        assert_eq!(listing.select(&tasks, &clock), vec![1, 2]);
+
+        listing.states = vec![];
+        listing.text = Some("REPORT".to_string());
+        assert_eq!(listing.select(&tasks, &clock), vec![0, 2]);
+
+        listing.tags = vec!["work".to_string()];
+        listing.project = Some("ops".to_string());
+        assert_eq!(listing.select(&tasks, &clock), vec![2]);
+
+        let mut listing = Listing::new();
+        listing.due = Some(DateRange::new(Timestamp::from_seconds(0),
+                                          Timestamp::from_seconds(4000)));
+        listing.all = true;
+        assert_eq!(listing.select(&tasks, &clock), vec![1, 2]);
+
+        listing.priority = Some(Priority::High);
+        assert_eq!(listing.select(&tasks, &clock), vec![2]);
+
+        let mut listing = Listing::new();
+        listing.filter = Filter::from_string("+work", &clock, &|_| None)
+            .unwrap();
+        assert_eq!(listing.select(&tasks, &clock), vec![0]);
+
+        listing.filter = Filter::from_string("status:waiting or mom", &clock, 
+                                             &|_| None).unwrap();
+        assert_eq!(listing.select(&tasks, &clock), vec![1, 2]);
+    }
+
+    #[test]
+    fn sorting() {
+        let tasks = tasks();
+        let clock = clock(2000);
+        let mut listing = Listing::new();
+        listing.all = true;
+
+        listing.sort = SortKey::from_string("due").unwrap();
+        assert_eq!(listing.select(&tasks, &clock), vec![1, 2, 0]);
+
+        listing.sort = SortKey::from_string("created").unwrap();
+        assert_eq!(listing.select(&tasks, &clock), vec![2, 0, 1]);
+
+        listing.sort = SortKey::from_string("priority,due").unwrap();
+        assert_eq!(listing.select(&tasks, &clock), vec![2, 0, 1]);
+
+        listing.limit = Some(2);
+        assert_eq!(listing.select(&tasks, &clock), vec![2, 0]);
+
+        assert!(SortKey::from_string("due,size").is_err());
+    }
+}
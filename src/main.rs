@@ -24,220 +24,902 @@
  
 extern crate rustc_serialize;
 extern crate time;
+extern crate libc;
+extern crate unicode_width;
 
 mod task;
 mod timestamp;
+mod tz;
+mod calendar;
+mod daterange;
+mod effort;
+mod reminder;
+mod daemon;
+mod filter;
+mod list;
+mod selector;
+mod diff;
+mod term;
 mod config;
 mod argparser;
+mod error;
+mod export;
+mod color;
+mod table;
+mod report;
+mod agenda;
+mod stats;
 
 use std::env;
+use std::io::{self, ErrorKind};
 use std::vec::Vec;
 use std::process::exit;
 use std::path::{PathBuf};
 use std::usize;
 
 use config::Config;
-use timestamp::Timestamp;
-use task::{Task, TaskFile};
-use argparser::{ArgParser};
+use timestamp::{Timestamp, Duration, DateFormat, Clock};
+use task::{Task, TaskList, TaskFile, State, Priority};
+use argparser::{ArgParser, Command, Matches, Invocation, Parsed};
+use tz::TimeZone;
+use calendar::Calendar;
+use effort::EffortReport;
+use list::{Listing, SortKey};
+use daterange::DateRange;
+use filter::Filter;
+use diff::Change;
+use error::Error;
+use export::Format;
+use color::{ColorMode, Style};
+use table::{Table, Column};
+use report::Report;
+use agenda::{Agenda, MonthView};
+use stats::Stats;
+use rustc_serialize::json::Json;
 
-fn ignore_args(cmd: &str, args: &[String]) {
-    if !args.is_empty() {
-        print!("tap: {}: ignoring superfluous argument(s) -", cmd);
+fn config_path() -> Result<PathBuf, Error> {
+    let mut path = try!(env::home_dir().ok_or_else(|| {
+        Error::io("$HOME", io::Error::new(ErrorKind::NotFound, 
+                                          "cannot find the home directory"))
+    }));
+    path.push(".config/tap/tap.conf");
+    
+    Ok(path)
+}
+
+fn parser() -> ArgParser {
+    let mut parser = ArgParser::new(Command::new("tap", "Very simple \
+        commandline task planer. Lists the tasks if no command is given.")
+        .flag("all", Some('a'), "also list waiting tasks")
+        .flag("dry-run", None, "show the changes instead of saving them")
+        .option("format", None, "<format>", "output \"text\" (default), \
+                \"json\" or \"ndjson\"")
+        .option("color", None, "<when>", "color the output \"auto\" (default), \
+                \"always\" or \"never\""));
+    
+    parser.command(Command::new("add", "add a new task, attributes are \
+        est:<duration>, remind:<durations>, pri:<H|M|L>, project:<name> and +<tag>")
+        .args("<description> [time] [attribute]...", 1, usize::MAX)
+        .flag("force", Some('f'), "accept a deadline in the past")
+        .option("scheduled", Some('s'), "<time>", "planned start of the work")
+        .option("wait", Some('w'), "<time>", "hide the task until then"));
+    parser.command(Command::new("agenda", "list the open tasks by day")
+        .option("days", Some('d'), "<n>", "number of days (default: 7)"));
+    parser.command(Command::new("calendar", "show the deadlines of a month")
+        .args("[month]", 0, 1));
+    parser.command(Command::new("complete", "mark tasks as completed")
+        .args("[selector]...", 0, usize::MAX)
+        .flag("all", Some('a'), "complete all tasks")
+        .option("at", None, "<time>", "completion time (default: now)")
+        .flag("yes", Some('y'), "do not ask for confirmation")
+        .flag("dry-run", None, "show the changes instead of saving them"));
+    parser.command(Command::new("daemon", 
+                                "notify about reminders and deadlines"));
+    parser.command(Command::new("effort", 
+                                "compare estimated and tracked time"));
+    parser.command(Command::new("file", "set the task file")
+        .args("<path>", 1, 1));
+    parser.command(Command::new("list", "list tasks matching the filter")
+        .args("[filter]...", 0, usize::MAX)
+        .flag("all", Some('a'), "also list waiting tasks")
+        .option("state", Some('s'), "<states>", 
+                "pending, waiting, missed or completed, e.g. \"missed,pending\"")
+        .option("due", Some('d'), "<range>", 
+                "deadline within the range, e.g. \"this-week\"")
+        .option("text", Some('t'), "<text>", "description contains the text")
+        .option("project", Some('p'), "<name>", "tasks of the project")
+        .option("tag", None, "<tag>", "tasks with the tag, may be repeated")
+        .option("priority", None, "<H|M|L>", "tasks with the priority")
+        .option("sort", None, "<keys>", "due, created or priority, e.g. \"due,priority\"")
+        .option("limit", Some('n'), "<n>", "list at most n tasks")
+        .option("columns", Some('c'), "<columns>", "show a table of id, state, \
+                priority, due, age, project, tags or description")
+        .flag("wrap", None, "wrap long descriptions instead of cutting them"));
+    parser.command(Command::new("modify", "change the selected tasks")
+        .args("<selector>...", 1, usize::MAX)
+        .option("text", Some('t'), "<text>", "new description")
+        .option("deadline", Some('d'), "<time>", "new deadline")
+        .flag("force", Some('f'), "accept a deadline in the past")
+        .option("scheduled", Some('s'), "<time>", "planned start of the work")
+        .option("wait", Some('w'), "<time>", "hide the tasks until then")
+        .option("set", None, "<attribute>", 
+                "e.g. \"pri:H\", \"+tag\" or \"-tag\", may be repeated")
+        .flag("yes", Some('y'), "do not ask for confirmation")
+        .flag("dry-run", None, "show the changes instead of saving them"));
+    parser.command(Command::new("remind", "deliver due reminders"));
+    parser.command(Command::new("remove", "remove tasks")
+        .args("[selector]...", 0, usize::MAX)
+        .flag("all", Some('a'), "remove all tasks")
+        .flag("all-completed", Some('c'), "remove all completed tasks")
+        .flag("yes", Some('y'), "do not ask for confirmation")
+        .flag("dry-run", None, "show the changes instead of saving them"));
+    parser.command(Command::new("show", "show all attributes of tasks")
+        .args("<selector>...", 1, usize::MAX));
+    parser.command(Command::new("stats", "summarize the tasks matching \
+        the filter and chart their progress")
+        .args("[filter]...", 0, usize::MAX)
+        .option("range", Some('r'), "<range>", 
+                "period of the chart (default: the last 4 weeks)"));
+    parser.command(Command::new("track", "log time spent on a task")
+        .args("<index> <duration>", 2, 2));
+    
+    parser
+}
+
+fn parse_time(s: &str, clock: &Clock) -> Result<Timestamp, Error> {
+    Timestamp::from_string(s, clock).map_err(|x| {
+        x.map_message(|y| format!("invalid time format \"{}\" - {}", s, y))
+    })
+}
+
+/* e.g. "est:2h", "remind:1d,1h", "pri:H", "project:name" or "+tag" */
+fn is_attribute(arg: &str) -> bool {
+    let prefixes = ["est:", "remind:", "pri:", "project:", "+"];
+    
+    !arg.contains(char::is_whitespace) 
+        && prefixes.iter().any(|x| arg.starts_with(x) && arg.len() > x.len())
+}
+
+fn set_attribute(task: &mut Task, arg: &str) -> Result<(), Error> {
+    if arg.starts_with("est:") {
+        let d = try!(Duration::from_string(&arg[4..]).map_err(|x| {
+            x.map_message(|y| format!("invalid estimate \"{}\" - {}", arg, y))
+        }));
         
-        for x in args.iter() {
-            print!(" \"{}\"", x);
+        task.set_estimate(d);
+    } else if arg.starts_with("remind:") {
+        if !task.deadline().valid() {
+            return Err(Error::Usage(format!("reminders require a deadline")));
         }
         
-        println!("");
+        for x in arg[7..].split(',') {
+            let d = try!(Duration::from_string(x).map_err(|err| {
+                err.map_message(|y| {
+                    format!("invalid reminder \"{}\" - {}", x, y)
+                })
+            }));
+            
+            if d < Duration::zero() {
+                return Err(Error::Usage(format!("invalid reminder \"{}\" - \
+                                                 reminders precede the \
+                                                 deadline", x)));
+            }
+            
+            task.add_reminder(d);
+        }
+    } else if arg.starts_with("pri:") {
+        task.set_priority(try!(Priority::from_string(&arg[4..])));
+    } else if arg.starts_with("project:") {
+        task.set_project(&arg[8..]);
+    } else if arg.starts_with("+") {
+        task.add_tag(&arg[1..]);
     }
+    
+    Ok(())
+}
 
+/* deadlines in the past need --force */
+fn set_deadline(m: &Matches, task: &mut Task, ts: Timestamp, clock: &Clock)
+                -> Result<(), Error> {
+    if m.flag("force") {
+        task.force_deadline(ts);
+        return Ok(());
+    }
+    
+    task.set_deadline(ts, clock).map_err(|x| {
+        x.map_message(|y| format!("{} - use --force to keep it", y))
+    })
 }
 
-fn missing_arg(cmd: &str) {
-    println!("tap: {}: missing argument(s)", cmd);
+fn add(m: &Matches, tasks: &mut TaskList, clock: &Clock, format: Format) 
+       -> Result<(), Error> {
+    let scheduled = try!(m.value_with("scheduled", |x| {
+        Timestamp::from_string(x, clock)
+    }));
+    let wait = try!(m.value_with("wait", |x| Timestamp::from_string(x, clock)));
+    
+    let mut task = Task::new(clock);
+    let mut params: Vec<&String> = vec![];
+    let mut attributes = vec![];
+    
+    /* everything after "--" is plain text */
+    for (i, x) in m.args().iter().enumerate() {
+        if !m.escaped(i) && is_attribute(x) {
+            attributes.push(x);
+        } else {
+            params.push(x);
+        }
+    }
+    
+    if params.is_empty() || params[0].is_empty() {
+        return Err(Error::Usage(format!("missing task description")));
+    }
+    
+    if params.len() > 2 {
+        let v: Vec<String> = params[2..].iter()
+            .map(|x| format!("\"{}\"", x))
+            .collect();
+        
+        return Err(Error::Usage(format!("superfluous argument(s) {}", 
+                                        v.join(" "))));
+    }
+    
+    task.set_text(params[0].as_ref());
+    
+    if params.len() > 1 {
+        let ts = try!(parse_time(params[1], clock));
+        try!(set_deadline(m, &mut task, ts, clock));
+    }
+    
+    if let Some(ts) = scheduled {
+        task.set_scheduled(ts);
+    }
+    
+    if let Some(ts) = wait {
+        task.set_wait(ts);
+    }
+    
+    /* reminders need the deadline */
+    for x in attributes {
+        try!(set_attribute(&mut task, x));
+    }
+    
+    tasks.add(task);
+    
+    let i = tasks.len() - 1;
+    
+    if format == Format::Text {
+        println!("added task {} \"{}\"", i + 1, tasks[i].text());
+    } else {
+        print!("{}", export::tasks(format, tasks, &[i], clock));
+    }
+    
+    Ok(())
 }
 
-fn home_dir() -> PathBuf {
-    env::home_dir().unwrap()
+/* looks up the filters saved as "filter.<name>" */
+fn saved_filter(conf: &Config, name: &str) -> Option<String> {
+    conf.get(&format!("filter.{}", name)).map(|x| x.to_string())
 }
 
-fn config_path() -> PathBuf {
-    let mut path = home_dir();
-    path.push(".config/tap/tap.conf");
-    
-    path
+fn parse_filter(s: &str, clock: &Clock, conf: &Config) 
+                -> Result<Filter, Error> {
+    Filter::from_string(s, clock, &|x| saved_filter(conf, x))
 }
 
-fn main() {
-    let mut conf = Config::new(&config_path()).unwrap();
-    let args : Vec<_> = env::args().collect();
+fn select(args: &[String], tasks: &TaskList, clock: &Clock, conf: &Config) 
+          -> Result<Vec<usize>, Error> {
+    selector::select(args, tasks, clock, &|x| saved_filter(conf, x))
+}
+
+/* lists the tasks and asks the user before changing them */
+fn confirm(m: &Matches, tasks: &TaskList, v: &[usize], clock: &Clock, 
+           action: &str, interactive: bool) -> Result<(), Error> {
+    if !interactive || m.flag("yes") || v.is_empty() {
+        return Ok(());
+    }
     
-    let mut parser = ArgParser::new();
-    parser.add_opt("add");
-    parser.add_opt("complete");
-    parser.add_opt("file");
-    parser.add_opt("remove");
+    eprint!("{}", tasks.display(clock, v.to_vec()));
     
-    let unknown = parser.parse(&args.as_slice(), 1..args.len());
-    if !unknown.is_empty() {
-        for x in &unknown {
-            println!("tap: unknown argument \"{}\"", x);
-        }
-        
-        exit(1);
+    if !term::confirm(&format!("{} {} task(s)?", action, v.len())) {
+        return Err(Error::Aborted);
     }
     
-    let mut filename = conf.task_file();
-    let mut name_changed = false;
+    Ok(())
+}
+
+/* 
+ * Every task as listed, followed by the exported fields the listing leaves
+ * out, e.g. "estimate", and the delivered reminders. Returns the lines and
+ * the index of the task of each line.
+ */
+fn diff_lines(tasks: &TaskList, clock: &Clock) -> (Vec<String>, Vec<usize>) {
+    let shown = ["id", "description", "state", "deadline", "completed"];
+    let mut lines = vec![];
+    let mut ids = vec![];
     
-    let file_info = parser.get_arginfo("file").unwrap();
-    if file_info.is_passed() {
-        if !file_info.has_args() {
-            missing_arg("file");
-            exit(1);
+    for (i, x) in tasks.iter().enumerate() {
+        let mut v = vec![format!("{}", x.display(clock))];
+        
+        if let Json::Object(obj) = export::task(i + 1, x, clock) {
+            for (key, value) in obj {
+                match value {
+                    Json::Null => continue,
+                    Json::Array(ref y) if y.is_empty() => continue,
+                    _ if shown.contains(&key.as_ref()) => continue,
+                    _ => v.push(format!("    {}: {}", key, value)),
+                }
+            }
         }
         
-        let mut range = file_info.range();
-        range.start += 1;
-        if range.start < range.end {
-            ignore_args("file", &args[range]);
+        let reminded: Vec<String> = x.reminded().iter()
+            .map(|y| y.seconds().to_string())
+            .collect();
+        
+        if !reminded.is_empty() {
+            v.push(format!("    reminded: [{}]", reminded.join(",")));
         }
-                
-        filename = args[file_info.begin()].clone();
         
-        /* delay writing to disk until we know we can actually use the file */
-        name_changed = true;
+        ids.extend(v.iter().map(|_| i));
+        lines.extend(v);
     }
     
-    let mut taskfile = match TaskFile::new(filename.as_ref()) {
-        Ok(x) => x,
-        Err(err) => { 
-            println!("tap: failed to load \"{}\" - {}", filename, err);
-            exit(1);
+    (lines, ids)
+}
+
+/* the tasks that a dry run would have removed (-) and added or changed (+) */
+fn print_diff(before: &TaskList, after: &TaskList, clock: &Clock) {
+    let clock = clock.frozen();
+    let (a, a_ids) = diff_lines(before, &clock);
+    let (b, b_ids) = diff_lines(after, &clock);
+    let changes = diff::diff(&a, &b);
+    
+    for x in &changes {
+        match *x {
+            Change::Removed(i, s) => println!("- {:2} : {}", a_ids[i] + 1, s),
+            Change::Added(i, s) => println!("+ {:2} : {}", b_ids[i] + 1, s),
         }
-    };
+    }
+    
+    println!("dry run - {} line(s) changed, the task file was left untouched",
+             changes.len());
+}
+
+/* e.g. "completed 2 task(s):" followed by the tasks */
+fn summary(tasks: &TaskList, v: &[usize], action: &str, format: Format, 
+           clock: &Clock) {
+    if format != Format::Text {
+        print!("{}", export::tasks(format, tasks, v, clock));
+        return;
+    }
     
-    let mut tasks = taskfile.load();
+    println!("{} {} task(s){}", action, v.len(), 
+             if v.is_empty() { "" } else { ":" });
     
-    if name_changed {
-        conf.set_task_file(&filename).unwrap();
+    for &i in v {
+        println!("  {:2} : \"{}\"", i + 1, tasks[i].text());
     }
+}
 
-    let add_info = parser.get_arginfo("add").unwrap();
-    if add_info.is_passed() {
-        if !add_info.has_args() {
-            missing_arg("add");
-            exit(1);
+fn complete(m: &Matches, tasks: &mut TaskList, clock: &Clock, conf: &Config,
+            interactive: bool, format: Format) -> Result<(), Error> {
+    let at = try!(m.value_with("at", |x| Timestamp::from_string(x, clock)))
+        .unwrap_or(clock.now());
+    
+    let v = if m.flag("all") {
+        (0..tasks.len()).collect()
+    } else if m.args().is_empty() {
+        return Err(Error::Usage(format!("missing argument(s) - expected \
+                                         [selector]...")));
+    } else {
+        try!(select(m.args(), tasks, clock, conf))
+    };
+    
+    if v.len() > 1 {
+        try!(confirm(m, tasks, &v, clock, "complete", interactive));
+    }
+    
+    try!(tasks.complete_many(&v, at, clock));
+    
+    summary(tasks, &v, "completed", format, clock);
+    
+    Ok(())
+}
+
+fn remove(m: &Matches, tasks: &mut TaskList, clock: &Clock, conf: &Config,
+          interactive: bool, format: Format) -> Result<(), Error> {
+    let v = if m.flag("all") {
+        (0..tasks.len()).collect()
+    } else if m.flag("all-completed") {
+        (0..tasks.len()).filter(|&i| tasks[i].is_completed()).collect()
+    } else if m.args().is_empty() {
+        return Err(Error::Usage(format!("missing argument(s) - expected \
+                                         [selector]...")));
+    } else {
+        try!(select(m.args(), tasks, clock, conf))
+    };
+    
+    try!(confirm(m, tasks, &v, clock, "remove", interactive));
+    
+    summary(tasks, &v, "removed", format, clock);
+    
+    tasks.remove_many(&v)
+}
+
+fn modify(m: &Matches, tasks: &mut TaskList, clock: &Clock, conf: &Config,
+          interactive: bool, format: Format) -> Result<(), Error> {
+    let v = try!(select(m.args(), tasks, clock, conf));
+    
+    let deadline = try!(m.value_with("deadline", |x| parse_time(x, clock)));
+    let scheduled = try!(m.value_with("scheduled", |x| {
+        Timestamp::from_string(x, clock)
+    }));
+    let wait = try!(m.value_with("wait", |x| Timestamp::from_string(x, clock)));
+    
+    /* "-tag" removes a tag */
+    for x in m.values("set") {
+        if !is_attribute(x) && !(x.starts_with('-') && x.len() > 1) {
+            return Err(Error::Usage(format!("invalid attribute \"{}\"", x)));
         }
+    }
+    
+    /* change copies of the tasks, so errors leave all tasks untouched */
+    let mut modified = vec![];
+    
+    for &i in &v {
+        let mut task = try!(tasks.get(i)).clone();
         
-        let mut i = add_info.begin();
-        let j = add_info.end();
+        if let Some(s) = m.value("text") {
+            task.set_text(s);
+        }
         
-        let mut task = Task::new();
-        task.set_text(args[i].as_ref());
+        if let Some(ts) = deadline {
+            try!(set_deadline(m, &mut task, ts, clock));
+        }
+        
+        if let Some(ts) = scheduled {
+            task.set_scheduled(ts);
+        }
         
-        i += 1;
+        if let Some(ts) = wait {
+            task.set_wait(ts);
+        }
         
-        if i < j {
-            let ts = Timestamp::from_string(&args[i]);
-            if ts.is_err() {
-                let s = ts.unwrap_err();
-                println!("tap: add: invalid time format \"{}\" - {}", 
-                         args[i], s);
-                exit(1);
+        for x in m.values("set") {
+            if x.starts_with('-') {
+                task.remove_tag(&x[1..]);
+            } else {
+                try!(set_attribute(&mut task, x));
             }
-            
-            task.set_deadline(ts.unwrap());
         }
         
-        i += 1;
+        modified.push((i, task));
+    }
+    
+    if v.len() > 1 {
+        try!(confirm(m, tasks, &v, clock, "modify", interactive));
+    }
+    
+    for (i, task) in modified {
+        *try!(tasks.get_mut(i)) = task;
+    }
+    
+    summary(tasks, &v, "modified", format, clock);
+    
+    Ok(())
+}
+
+fn track(m: &Matches, tasks: &mut TaskList, clock: &Clock, format: Format) 
+         -> Result<(), Error> {
+    let i = try!(selector::parse_index(&m.args()[0], tasks.len()));
+    
+    let s = &m.args()[1];
+    let d = try!(Duration::from_string(s).map_err(|x| {
+        x.map_message(|y| format!("invalid duration \"{}\" - {}", s, y))
+    }));
+    
+    try!(tasks.get_mut(i)).track(d);
+    
+    if format == Format::Text {
+        println!("tracked {} on task {}, {} in total", d, i + 1, 
+                 tasks[i].tracked());
+    } else {
+        print!("{}", export::tasks(format, tasks, &[i], clock));
+    }
+    
+    Ok(())
+}
+
+fn list(m: &Matches, tasks: &TaskList, clock: &Clock, conf: &Config, 
+        all: bool, format: Format) -> Result<(), Error> {
+    let mut listing = Listing::new();
+    
+    listing.filter = try!(parse_filter(&m.args().join(" "), clock, conf));
+    listing.all = all || m.flag("all");
+    listing.states = try!(m.value_with("state", |x| {
+        x.split(',').map(|y| State::from_string(y.trim())).collect()
+    })).unwrap_or(vec![]);
+    listing.due = try!(m.value_with("due", |x| DateRange::from_string(x, clock)));
+    listing.text = m.value("text").map(|x| x.to_string());
+    listing.project = m.value("project").map(|x| x.to_string());
+    listing.tags = m.values("tag").iter().map(|x| x.to_string()).collect();
+    listing.priority = try!(m.value_with("priority", Priority::from_string));
+    listing.sort = try!(m.value_with("sort", SortKey::from_string))
+        .unwrap_or(vec![]);
+    listing.limit = try!(m.value_with("limit", |x| {
+        usize::from_str_radix(x, 10).map_err(|_| format!("expected a number"))
+    }));
+    
+    let v = listing.select(tasks, clock);
+    
+    /* the configured columns replace the classic listing */
+    let columns = m.value("columns").or(conf.get("columns"));
+    
+    if let (Format::Text, Some(s)) = (format, columns) {
+        let mut table = Table::new(try!(Column::from_string(s)));
+        table.width = term::width();
+        table.wrap = m.flag("wrap") 
+            || conf.get("wrap_descriptions") == Some("yes");
+        
+        print!("{}", table.render(tasks, &v, clock));
+    } else if format == Format::Text {
+        print!("{}", tasks.display(clock, v));
+    } else {
+        print!("{}", export::tasks(format, tasks, &v, clock));
+    }
+    
+    let waiting = tasks.waiting(clock);
+    if listing.hides_waiting() && waiting > 0 {
+        eprintln!("tap: {} waiting task(s) hidden - use --all to show them", 
+                  waiting);
+    }
+    
+    Ok(())
+}
+
+fn show(m: &Matches, tasks: &TaskList, clock: &Clock, conf: &Config, 
+        format: Format) -> Result<(), Error> {
+    let v = try!(select(m.args(), tasks, clock, conf));
+    
+    if format != Format::Text {
+        print!("{}", export::tasks(format, tasks, &v, clock));
+        return Ok(());
+    }
+    
+    for &i in &v {
+        let task = &tasks[i];
+        let mut fields = vec![("created", task.created().to_string())];
+        
+        let times = [
+            ("deadline", Some(task.deadline())),
+            ("completed", Some(task.completed())),
+            ("scheduled", task.scheduled()),
+            ("wait", task.wait()),
+        ];
+        
+        for &(name, ts) in &times {
+            match ts {
+                Some(x) if x.valid() => fields.push((name, x.to_string())),
+                _ => (),
+            }
+        }
+        
+        if let Some(d) = task.estimate() {
+            fields.push(("estimate", d.to_string()));
+        }
+        
+        fields.push(("tracked", task.tracked().to_string()));
         
-        if i < j {
-            ignore_args("add", &args[i..j]);
+        let reminders: Vec<String> = task.reminders().iter()
+            .map(|x| x.to_string())
+            .collect();
+        
+        if !reminders.is_empty() {
+            fields.push(("reminders", reminders.join(", ")));
         }
-                    
-        if task.text().is_empty() {
-            println!("tap: add: missing task description");
-            exit(1);
+        
+        print!("{}", tasks.display(clock, vec![i]));
+        
+        for (name, value) in fields {
+            println!("      {:<10} {}", format!("{}:", name), value);
         }
+    }
+    
+    Ok(())
+}
+
+fn agenda(m: &Matches, tasks: &TaskList, clock: &Clock) -> Result<(), Error> {
+    let days = try!(m.value_with("days", agenda::parse_days)).unwrap_or(7);
+    
+    print!("{}", Agenda::new(tasks, clock, days));
+    
+    Ok(())
+}
+
+fn calendar(m: &Matches, tasks: &TaskList, clock: &Clock) 
+            -> Result<(), Error> {
+    let view = match m.args().get(0) {
+        Some(s) => try!(MonthView::from_string(s, tasks, clock)),
+        None => MonthView::current(tasks, clock),
+    };
+    
+    print!("{}", view);
+    
+    Ok(())
+}
+
+fn stats(m: &Matches, tasks: &TaskList, clock: &Clock, conf: &Config) 
+         -> Result<(), Error> {
+    let filter = try!(parse_filter(&m.args().join(" "), clock, conf));
+    let range = try!(m.value_with("range", |x| {
+        DateRange::from_string(x, clock)
+    })).unwrap_or(stats::recent(clock));
+    
+    let v: Vec<&Task> = filter.select(tasks, clock).into_iter()
+        .map(|i| &tasks[i])
+        .collect();
+    
+    print!("{}", Stats::new(v, clock, range));
+    
+    Ok(())
+}
+
+fn remind(tasks: &mut TaskList, command: Option<&str>, clock: &Clock) {
+    let now = clock.now();
+
+    for x in reminder::due(tasks, clock) {
+        let msg = reminder::message(&tasks[x.index], &now);
         
-        tasks.add(task);
+        /* undelivered reminders are retried on the next check */
+        match reminder::deliver(&msg, command) {
+            Ok(_) => tasks[x.index].set_reminded(x.offset),
+            Err(err) => eprintln!("tap: remind: {}", err),
+        }
     }
+}
 
-    let complete_info = parser.get_arginfo("complete").unwrap();
-    if complete_info.is_passed() {
+/* reports the error on stderr and exits with its exit code */
+fn fail(context: &str, err: Error) -> ! {
+    eprintln!("tap: {}: {}", context, err);
+    exit(err.exit_code());
+}
 
-        for i in complete_info.range() {
-            let arg = &args[i];
-            
-            match arg.as_ref() {
-                "--all" => {
-                    tasks.complete_all();
-                    break;
-                },
-                _ => { 
-                    let result = usize::from_str_radix(arg, 10);
-                    if result.is_err() {
-                        println!("tap: complete: invalid argument \"{}\"", arg);
-                        exit(1);
-                    }
-                    
-                    tasks.complete(result.unwrap() - 1);
-                },
-            }
+/* prints the help or errors and exits */
+fn parse_args(parser: &ArgParser, args: &[String]) -> Invocation {
+    match parser.parse(args) {
+        Ok(Parsed::Run(x)) => x,
+        Ok(Parsed::Help(s)) => {
+            print!("{}", s);
+            exit(0);
+        },
+        Err(err) => {
+            eprintln!("tap: {}", err);
+            exit(error::EXIT_USAGE);
         }
     }
+}
+
+/* colors are configured as "color.<state> = <style>" */
+fn set_colors(conf: &Config, when: Option<&str>) -> Result<(), Error> {
+    let mode = match when.or(conf.get("color")) {
+        Some(s) => try!(ColorMode::from_string(s)),
+        None => ColorMode::Auto,
+    };
     
-    let remove_info = parser.get_arginfo("remove").unwrap();
-    if remove_info.is_passed() {
-        if !remove_info.has_args() {
-            missing_arg("remove");
-            exit(1);
+    if !mode.enabled(term::stdout_is_tty()) {
+        color::set_default(None);
+        return Ok(());
+    }
+    
+    let mut theme = color::Theme::new();
+    
+    for &state in &[State::Pending, State::Waiting, State::Missed, 
+                    State::Completed] {
+        if let Some(s) = conf.get(&format!("color.{}", state)) {
+            theme.set_style(state, try!(Style::from_string(s)));
         }
-        
-        let mut v = vec![];
-        
-        for i in remove_info.range() {
-            let arg = &args[i];
+    }
+    
+    color::set_default(Some(theme));
+    
+    Ok(())
+}
 
-            match arg.as_ref() {
-                "--all" => {
-                    tasks.remove_all();
-                    break;
-                }
-                "--all-completed" => {
-                    for i in 0..tasks.len() {
-                        if tasks[i].is_completed() {
-                            v.push(i);
-                        }
-                    }
-                    
-                    break;
-                }
-                _ => {
-                    let result = usize::from_str_radix(arg, 10);
-                    if result.is_err() {
-                        println!("tap: remove: invalid argument \"{}\"", arg);
-                        exit(1);
-                    }
-                    
-                    v.push(result.unwrap());
-                }
-            }
+fn output_format(invocation: &Invocation) -> Option<Format> {
+    match invocation.global.value_with("format", Format::from_string) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("tap: {}", err);
+            exit(error::EXIT_USAGE);
+        }
+    }
+}
+
+fn main() {
+    let mut conf = match config_path().and_then(|x| Config::new(&x)) {
+        Ok(x) => x,
+        Err(err) => fail("config", err),
+    };
+    
+    if let Some(name) = conf.get("timezone") {
+        match TimeZone::from_name(name) {
+            Ok(x) => tz::set_default(x),
+            Err(err) => fail("config", Error::from(err)),
+        }
+    }
+    
+    let mut style = timestamp::date_style();
+    
+    if let Some(s) = conf.get("date_format") {
+        match DateFormat::from_string(s) {
+            Ok(x) => style.format = x,
+            Err(err) => fail("config", Error::from(err)),
+        }
+    }
+    
+    if let Some(s) = conf.get("relative_hours") {
+        match Duration::from_string(s) {
+            Ok(x) => style.hours = x,
+            Err(err) => fail("config: relative_hours", Error::from(err)),
+        }
+    }
+    
+    if let Some(s) = conf.get("relative_limit") {
+        match Duration::from_string(s) {
+            Ok(x) => style.limit = x,
+            Err(err) => fail("config: relative_limit", Error::from(err)),
         }
+    }
+    
+    timestamp::set_date_style(style);
+    
+    let mut cal = Calendar::new();
+    
+    let result = conf.get("workdays").map_or(Ok(()), |x| cal.set_workdays(x))
+        .and(conf.get("holidays").map_or(Ok(()), |x| cal.set_holidays(x)))
+        .and(conf.get("work_hours").map_or(Ok(()), |x| cal.set_work_hours(x)));
+    
+    if let Err(err) = result {
+        fail("config", Error::Usage(err));
+    }
+    
+    calendar::set_default(cal);
+    
+    let clock = match Clock::from_env() {
+        Ok(x) => x,
+        Err(err) => fail("TAP_NOW", err),
+    };
+    let args : Vec<_> = env::args().skip(1).collect();
+    
+    let mut parser = parser();
+    
+    /* reports run as commands of their own, e.g. "tap next" */
+    for (name, s) in conf.entries("report.") {
+        match Report::from_string(s) {
+            Ok(x) => {
+                let help = x.description.unwrap_or(format!("report: {}", s));
+                parser.alias(name, &help, x.args);
+            },
+            Err(err) => fail(&format!("config: report.{}", name), err),
+        }
+    }
+    
+    let mut invocation = parse_args(&parser, &args);
+    let mut all = invocation.global.flag("all");
+    let mut dry_run = invocation.global.flag("dry-run");
+    let mut format = output_format(&invocation);
+    let mut when = invocation.global.value("color").map(|x| x.to_string());
+    
+    /* plain "tap" runs the configured default command */
+    if invocation.command.is_none() {
+        let v: Vec<String> = conf.get("default_command").unwrap_or("list")
+            .split_whitespace()
+            .map(|x| x.to_string())
+            .collect();
         
-        if !v.is_empty() && tasks.len() > 0 {
-            v.sort_by(|a, b| b.cmp(a));
-            v.dedup();
-            
-            for x in v {
-                tasks.remove(x);
-            }
+        invocation = parse_args(&parser, &v);
+        all = all || invocation.global.flag("all");
+        dry_run = dry_run || invocation.global.flag("dry-run");
+        format = format.or(output_format(&invocation));
+        when = when.or(invocation.global.value("color").map(|x| x.to_string()));
+    }
+    
+    let m = &invocation.matches;
+    let cmd = invocation.command.unwrap_or("list");
+    
+    /* the per-command --dry-run means the same as the global one */
+    dry_run = dry_run || m.flag("dry-run");
+    let interactive = !dry_run && term::stdin_is_tty();
+    let format = format.unwrap_or(Format::Text);
+    
+    if let Err(err) = set_colors(&conf, when.as_ref().map(|x| x.as_ref())) {
+        fail("color", err);
+    }
+    
+    let structured = [
+        "add", "complete", "list", "modify", "remove", "show", "track"
+    ];
+    
+    if format != Format::Text && !structured.contains(&cmd) {
+        fail(cmd, Error::Usage(format!("--format is not supported - use \
+                                        \"list\" or \"show\" instead")));
+    }
+    
+    let filename = match cmd {
+        "file" => m.args()[0].clone(),
+        _ => conf.task_file(),
+    };
+    
+    let mut taskfile = match TaskFile::new(filename.as_ref()) {
+        Ok(x) => x,
+        Err(err) => fail(cmd, err),
+    };
+    
+    let mut tasks = match taskfile.load() {
+        Ok(x) => x,
+        Err(err) => fail(cmd, err),
+    };
+    let before = tasks.clone();
+    
+    /* delay writing to disk until we know we can actually use the file */
+    if cmd == "file" && !dry_run {
+        if let Err(err) = conf.set_task_file(&filename) {
+            fail(cmd, err);
         }
+        
+        println!("using task file \"{}\"", filename);
     }
     
-    print!("{}", tasks);
+    let result = match cmd {
+        "add" => add(m, &mut tasks, &clock, format),
+        "agenda" => agenda(m, &tasks, &clock),
+        "calendar" => calendar(m, &tasks, &clock),
+        "show" => show(m, &tasks, &clock, &conf, format),
+        "stats" => stats(m, &tasks, &clock, &conf),
+        "complete" => {
+            complete(m, &mut tasks, &clock, &conf, interactive, format)
+        },
+        "list" => list(m, &tasks, &clock, &conf, all, format),
+        "modify" => modify(m, &mut tasks, &clock, &conf, interactive, format),
+        "remove" => remove(m, &mut tasks, &clock, &conf, interactive, format),
+        "track" => track(m, &mut tasks, &clock, format),
+        _ => Ok(()),
+    };
+    
+    if let Err(err) = result {
+        fail(cmd, err);
+    }
     
-    taskfile.save(&tasks);
+    match cmd {
+        "daemon" if dry_run => {
+            fail(cmd, Error::Usage(format!("--dry-run is not supported")));
+        },
+        "daemon" => {
+            if let Err(err) = daemon::run(&filename, 
+                                          conf.get("notify_command"), &clock) {
+                fail(cmd, err);
+            }
+        },
+        "effort" => print!("{}", EffortReport::new(&tasks, &clock)),
+        "remind" => {
+            /* a dry run only prints the reminders it would deliver */
+            let command = if dry_run { None } else { conf.get("remind_command") };
+            remind(&mut tasks, command, &clock);
+        },
+        _ => (),
+    }
+    
+    if dry_run {
+        if format == Format::Text {
+            print_diff(&before, &tasks, &clock);
+        } else {
+            eprintln!("tap: dry run - the task file was left untouched");
+        }
+        
+        return;
+    }
+    
+    if let Err(err) = taskfile.save(&tasks) {
+        fail(cmd, err);
+    }
 }
@@ -0,0 +1,156 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::process::Command;
+
+use task::{Task, TaskList};
+use timestamp::{Timestamp, Duration, Clock};
+use timestamp;
+
+pub struct Reminder {
+    pub index: usize,
+    pub offset: Duration,
+}
+
+/* reminders that have become due but were not delivered yet */
+pub fn due(tasks: &TaskList, clock: &Clock) -> Vec<Reminder> {
+    let mut result = vec![];
+
+    for (i, task) in tasks.iter().enumerate() {
+        for d in task.due_reminders(clock) {
+            result.push(Reminder { index: i, offset: d });
+        }
+    }
+
+    result
+}
+
+/* the point in time at which the next reminder becomes due */
+pub fn next(tasks: &TaskList, now: &Timestamp) -> Option<Timestamp> {
+    tasks.iter()
+        .flat_map(|x| {
+            let deadline = x.deadline();
+
+            x.pending_reminders().into_iter().map(move |d| deadline - d)
+        })
+        .filter(|x| x > now)
+        .min()
+}
+
+pub fn message(task: &Task, now: &Timestamp) -> String {
+    let deadline = task.deadline();
+    let style = timestamp::date_style();
+
+    format!("\"{}\" -- deadline {} ({})", task.text(), deadline,
+            deadline.relative(now, &style, "overdue"))
+}
+
+/*
+ * Prints the message or passes it as the last argument to "command",
+ * which is run by the shell, e.g. "notify-send tap".
+ */
+pub fn deliver(message: &str, command: Option<&str>) -> Result<(), String> {
+    let command = match command {
+        Some(x) => x,
+        None => {
+            println!("{}", message);
+            return Ok(());
+        }
+    };
+
+    let status = try!(Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", command))
+        .arg("sh")
+        .arg(message)
+        .status()
+        .map_err(|x| format!("failed to run \"{}\" - {}", command, x)));
+
+    if !status.success() {
+        return Err(format!("\"{}\" failed - {}", command, status));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList};
+    use task::fixture::clock;
+    use task::fixture;
+    use timestamp::{Timestamp, Duration};
+
+    fn task(deadline: i64, reminders: &[i64]) -> Task {
+        let mut task = fixture::task("x", deadline);
+
+        for &x in reminders {
+            task.add_reminder(Duration::minutes(x));
+        }
+
+        task
+    }
+
+    #[test]
+    fn due_reminders() {
+        let mut tasks = TaskList::new();
+        tasks.add(task(10000, &[60, 10]));
+        tasks.add(task(10000, &[]));
+        tasks.add(task(7000, &[30]));
+
+        let clock = clock(9000);
+        let v = due(&tasks, &clock);
+
+        assert_eq!(v.len(), 2);
+        assert_eq!((v[0].index, v[0].offset), (0, Duration::minutes(60)));
+        assert_eq!((v[1].index, v[1].offset), (2, Duration::minutes(30)));
+
+        /* completed after its deadline */
+        tasks[2].set_completed(Timestamp::from_seconds(8000), &clock).unwrap();
+        assert_eq!(due(&tasks, &clock).len(), 1);
+        assert!(tasks[2].pending_reminders().is_empty());
+    }
+
+    #[test]
+    fn next_reminder() {
+        let mut tasks = TaskList::new();
+        tasks.add(task(10000, &[60, 10]));
+        tasks.add(task(8000, &[30]));
+
+        let now = Timestamp::from_seconds(6000);
+        assert_eq!(next(&tasks, &now), Some(Timestamp::from_seconds(6200)));
+
+        tasks[1].set_reminded(Duration::minutes(30));
+        assert_eq!(next(&tasks, &now), Some(Timestamp::from_seconds(6400)));
+
+        let now = Timestamp::from_seconds(9500);
+        assert_eq!(next(&tasks, &now), None);
+    }
+
+    #[test]
+    fn command() {
+        assert!(deliver("x", Some("test \"x\" =")).is_ok());
+        assert!(deliver("y", Some("test \"x\" =")).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use error::Error;
+
+/* 
+ * A report is a saved listing, e.g. 
+ * 
+ *   filter:"status:pending" sort:"due,priority" columns:"id,due" limit:10
+ * 
+ * It runs as "tap list" with the options of the report, so arguments 
+ * given on the command line refine the filter or override the options.
+ */
+pub struct Report {
+    pub description: Option<String>,
+    pub args: Vec<String>,
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().map_or(false, |x| x.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/* "key:value" pairs, values may be quoted to contain spaces */
+fn pairs(s: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut v = vec![];
+    let mut chars = s.chars().peekable();
+    
+    loop {
+        skip_whitespace(&mut chars);
+        
+        if chars.peek().is_none() {
+            return Ok(v);
+        }
+        
+        let mut key = String::new();
+        
+        while let Some(x) = chars.next() {
+            if x == ':' {
+                break;
+            }
+            
+            if x.is_whitespace() || chars.peek().is_none() {
+                return Err(Error::Usage(format!("expected key:value \
+                                                 instead of \"{}{}\"", 
+                                                key, x)));
+            }
+            
+            key.push(x);
+        }
+        
+        let mut value = String::new();
+        
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(x) => value.push(x),
+                    None => {
+                        return Err(Error::Usage(format!("missing \" after \
+                                                         {}:\"{}", key, value)));
+                    },
+                }
+            }
+        } else {
+            while chars.peek().map_or(false, |x| !x.is_whitespace()) {
+                value.push(chars.next().unwrap());
+            }
+        }
+        
+        v.push((key, value));
+    }
+}
+
+impl Report {
+    pub fn from_string(s: &str) -> Result<Report, Error> {
+        let mut report = Report { 
+            description: None, 
+            args: vec![format!("list")], 
+        };
+        let mut filter = None;
+        
+        for (key, value) in try!(pairs(s)) {
+            match key.as_ref() {
+                "filter" => filter = Some(value),
+                "sort" | "columns" | "limit" | "state" | "due" => {
+                    report.args.push(format!("--{}={}", key, value));
+                },
+                "wrap" if value == "yes" => report.args.push(format!("--wrap")),
+                "wrap" => (),
+                "all" if value == "yes" => report.args.push(format!("--all")),
+                "all" => (),
+                "description" => report.description = Some(value),
+                _ => {
+                    return Err(Error::Usage(format!("unknown report setting \
+                                                     \"{}\"", key)));
+                },
+            }
+        }
+        
+        /* keep "a or b" apart from filters added on the command line */
+        if let Some(x) = filter {
+            if !x.trim().is_empty() {
+                report.args.push(format!("({})", x));
+            }
+        }
+        
+        Ok(report)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn args(s: &str) -> Vec<String> {
+        Report::from_string(s).unwrap().args
+    }
+    
+    #[test]
+    fn settings() {
+        assert_eq!(args("filter:\"status:pending or +urgent\" sort:due,priority \
+                         columns:\"id,due,description\" limit:10"),
+                   ["list", "--sort=due,priority", 
+                    "--columns=id,due,description", "--limit=10", 
+                    "(status:pending or +urgent)"]);
+        assert_eq!(args("  wrap:yes all:no  "), ["list", "--wrap"]);
+        assert_eq!(args(""), ["list"]);
+        
+        let report = Report::from_string("description:\"what's next\"").unwrap();
+        assert_eq!(report.description, Some(format!("what's next")));
+    }
+    
+    #[test]
+    fn errors() {
+        assert!(Report::from_string("filter").is_err());
+        assert!(Report::from_string("filter :x").is_err());
+        assert!(Report::from_string("filter:\"+x").is_err());
+        assert!(Report::from_string("size:10").is_err());
+    }
+}
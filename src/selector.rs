@@ -0,0 +1,188 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use task::TaskList;
+use timestamp::Clock;
+use filter::Filter;
+use error::Error;
+
+fn parse_number(s: &str) -> Option<usize> {
+    if s.is_empty() || !s.chars().all(|x| x.is_digit(10)) {
+        return None;
+    }
+
+    usize::from_str_radix(s, 10).ok()
+}
+
+/* anything starting with a digit is meant as an index, e.g. "4x" */
+fn looks_like_index(s: &str) -> bool {
+    s.chars().next().map_or(false, |x| x.is_digit(10))
+}
+
+/* e.g. "3", "3-7" or "1,3,5-7" */
+fn is_index_list(s: &str) -> bool {
+    s.split(',').all(|x| {
+        let v: Vec<&str> = x.splitn(2, '-').collect();
+
+        v.iter().all(|y| parse_number(y).is_some())
+    })
+}
+
+fn check_index(i: usize, len: usize) -> Result<usize, Error> {
+    if i == 0 {
+        return Err(Error::Usage(format!("invalid index 0 - tasks are \
+                                         numbered from 1")));
+    }
+
+    if i > len {
+        return Err(Error::NotFound(format!("task {} does not exist - there \
+                                            are {} task(s)", i, len)));
+    }
+
+    Ok(i - 1)
+}
+
+/* a single index as listed by "tap list" */
+pub fn parse_index(s: &str, len: usize) -> Result<usize, Error> {
+    match parse_number(s) {
+        Some(i) => check_index(i, len),
+        None => Err(Error::Usage(format!("invalid index \"{}\"", s))),
+    }
+}
+
+fn parse_index_list(s: &str, len: usize, v: &mut Vec<usize>) 
+                    -> Result<(), Error> {
+    for x in s.split(',') {
+        let bounds: Vec<usize> = x.splitn(2, '-')
+            .map(|y| parse_number(y).unwrap())
+            .collect();
+
+        let (begin, end) = match bounds.len() {
+            1 => (bounds[0], bounds[0]),
+            _ => (bounds[0], bounds[1]),
+        };
+
+        if begin > end {
+            return Err(Error::Usage(format!("invalid range \"{}\"", x)));
+        }
+
+        let begin = try!(check_index(begin, len));
+        let end = try!(check_index(end, len));
+
+        for i in begin..end + 1 {
+            if !v.contains(&i) {
+                v.push(i);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/*
+ * Selects tasks by their indices, ranges and lists of both, e.g. 
+ * "1,3 5-7", or by a filter expression, e.g. "project:ops and +urgent".
+ * If the first argument looks like an index, all arguments must be indices.
+ * All indices are validated, the result holds every task only once.
+ */
+pub fn select(args: &[String], tasks: &TaskList, clock: &Clock, 
+              saved: &Fn(&str) -> Option<String>) 
+              -> Result<Vec<usize>, Error> {
+    if !args.first().map_or(false, |x| looks_like_index(x)) {
+        let filter = try!(Filter::from_string(&args.join(" "), clock, saved));
+
+        return Ok(filter.select(tasks, clock));
+    }
+
+    let mut v = vec![];
+
+    for x in args {
+        if !is_index_list(x) {
+            return Err(Error::Usage(format!("invalid index \"{}\"", x)));
+        }
+
+        try!(parse_index_list(x, tasks.len(), &mut v));
+    }
+
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList};
+    use task::fixture::clock;
+    use error::{EXIT_USAGE, EXIT_NOT_FOUND};
+
+    /* errors are reduced to their exit code */
+    fn select_str(s: &str) -> Result<Vec<usize>, i32> {
+        let clock = clock(1000);
+        let mut tasks = TaskList::new();
+
+        for x in 0..10 {
+            let mut task = Task::new(&clock);
+            task.set_text(&format!("task {}", x));
+
+            if x % 3 == 0 {
+                task.add_tag("third");
+            }
+
+            tasks.add(task);
+        }
+
+        let args: Vec<String> = s.split(' ').map(|x| x.to_string()).collect();
+
+        select(&args, &tasks, &clock, &|_| None).map_err(|x| x.exit_code())
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(select_str("3"), Ok(vec![2]));
+        assert_eq!(select_str("3 1"), Ok(vec![2, 0]));
+        assert_eq!(select_str("3-5"), Ok(vec![2, 3, 4]));
+        assert_eq!(select_str("1,3,5-7"), Ok(vec![0, 2, 4, 5, 6]));
+        assert_eq!(select_str("1-3 2,3,4"), Ok(vec![0, 1, 2, 3]));
+        assert_eq!(select_str("10"), Ok(vec![9]));
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(select_str("+third"), Ok(vec![0, 3, 6, 9]));
+        assert_eq!(select_str("+third and not 9"), Ok(vec![0, 3, 6]));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(select_str("0"), Err(EXIT_USAGE));
+        assert_eq!(select_str("11"), Err(EXIT_NOT_FOUND));
+        assert_eq!(select_str("1 8-11"), Err(EXIT_NOT_FOUND));
+        assert_eq!(select_str("7-3"), Err(EXIT_USAGE));
+        assert_eq!(select_str("0-2"), Err(EXIT_USAGE));
+        assert_eq!(select_str("3 4x"), Err(EXIT_USAGE));
+        assert_eq!(select_str("3 +third"), Err(EXIT_USAGE));
+        assert_eq!(select_str("4x"), Err(EXIT_USAGE));
+        assert_eq!(select_str("1-18446744073709551615"), Err(EXIT_NOT_FOUND));
+        assert_eq!(select_str("status:bogus"), Err(EXIT_USAGE));
+    }
+}
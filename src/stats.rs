@@ -0,0 +1,312 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::cmp::{min, max};
+
+use task::{Task, State};
+use timestamp::{Timestamp, Duration, Clock};
+use daterange::DateRange;
+use tz;
+
+/* rows and width of the burnup chart */
+const CHART_ROWS: i64 = 30;
+const CHART_WIDTH: usize = 40;
+
+pub struct Stats<'a> {
+    tasks: Vec<&'a Task>,
+    clock: Clock,
+    range: DateRange,
+}
+
+/* the tasks created and completed before the end of a row of the chart */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Point {
+    pub day: i64,
+    pub created: usize,
+    pub completed: usize,
+}
+
+/* the last four weeks, including today */
+pub fn recent(clock: &Clock) -> DateRange {
+    let tz = tz::default();
+    let today = clock.now().day(&tz);
+    
+    DateRange::new(Timestamp::from_day(today - 27, 0, &tz), 
+                   Timestamp::from_day(today + 1, 0, &tz))
+}
+
+fn percent(n: usize, total: usize) -> i64 {
+    (n as f64 * 100.0 / total as f64).round() as i64
+}
+
+impl<'a> Stats<'a> {
+    pub fn new(tasks: Vec<&'a Task>, clock: &Clock, range: DateRange) 
+               -> Stats<'a> {
+        Stats { tasks: tasks, clock: *clock, range: range }
+    }
+    
+    pub fn count(&self, state: State) -> usize {
+        self.tasks.iter().filter(|x| x.state(&self.clock) == state).count()
+    }
+    
+    /* completed at all, late or in time */
+    pub fn completed(&self) -> Vec<&'a Task> {
+        self.tasks.iter()
+            .filter(|x| x.completed().valid())
+            .map(|x| *x)
+            .collect()
+    }
+    
+    pub fn late(&self) -> usize {
+        self.tasks.iter().filter(|x| x.completed_late()).count()
+    }
+    
+    /* the average time from creating a task to completing it */
+    pub fn lead_time(&self) -> Option<Duration> {
+        let done = self.completed();
+        
+        if done.is_empty() {
+            return None;
+        }
+        
+        let total: Duration = done.iter()
+            .map(|x| x.completed() - x.created())
+            /* back-dated completions may precede the creation */
+            .map(|x| if x < Duration::zero() { Duration::zero() } else { x })
+            .sum();
+        
+        Some(Duration::from_seconds(total.seconds() / done.len() as i64))
+    }
+    
+    /* 
+     * The range is cut to the days since the first task was created and 
+     * up to today, each point covers the same number of days.
+     */
+    pub fn burnup(&self) -> Vec<Point> {
+        let tz = tz::default();
+        let today = self.clock.now().day(&tz);
+        
+        let first = match self.tasks.iter().map(|x| x.created()).min() {
+            Some(x) => x.day(&tz),
+            None => return vec![],
+        };
+        
+        let begin = max(self.range.begin(), Timestamp::from_day(first, 0, &tz));
+        let end = min(self.range.end(), Timestamp::from_day(today + 1, 0, &tz));
+        
+        if begin >= end {
+            return vec![];
+        }
+        
+        /* the day of the last second still in the range */
+        let last = end - Duration::from_seconds(1);
+        let (begin, end) = (begin.day(&tz), last.day(&tz) + 1);
+        let step = max(1, (end - begin + CHART_ROWS - 1) / CHART_ROWS);
+        
+        let mut points = vec![];
+        let mut day = begin;
+        
+        while day < end {
+            let last = min(day + step, end);
+            let until = min(Timestamp::from_day(last, 0, &tz), self.range.end());
+            
+            points.push(Point {
+                day: day,
+                created: self.tasks.iter()
+                    .filter(|x| x.created() < until)
+                    .count(),
+                /* a task counts as completed once it was created, too */
+                completed: self.tasks.iter()
+                    .filter(|x| x.created() < until)
+                    .filter(|x| x.completed().valid() && x.completed() < until)
+                    .count(),
+            });
+            
+            day = last;
+        }
+        
+        points
+    }
+}
+
+impl<'a> Display for Stats<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let total = self.tasks.len();
+        let done = self.completed().len();
+        
+        try!(writeln!(f, "tasks     : {} -- {} pending, {} waiting, {} missed, \
+                          {} completed", total, self.count(State::Pending), 
+                      self.count(State::Waiting), self.count(State::Missed),
+                      self.count(State::Completed)));
+        
+        if total > 0 {
+            try!(write!(f, "completed : {} of {} ({}%)", 
+                        done, total, percent(done, total)));
+        }
+        
+        if done > 0 {
+            try!(write!(f, ", {} late ({}%)", 
+                        self.late(), percent(self.late(), done)));
+        }
+        
+        if total > 0 {
+            try!(writeln!(f, ""));
+        }
+        
+        if let Some(d) = self.lead_time() {
+            try!(writeln!(f, "lead time : {} on average", d));
+        }
+        
+        let points = self.burnup();
+        let scale = points.iter().map(|x| x.created).max().unwrap_or(0);
+        
+        if points.is_empty() || scale == 0 {
+            return Ok(());
+        }
+        
+        try!(writeln!(f, "\n{:10} : {:>4} : {:>4} : # completed, - open", 
+                      "", "open", "done"));
+        
+        let tz = tz::default();
+        
+        for x in points {
+            let tm = Timestamp::from_day(x.day, 0, &tz).to_tm(&tz);
+            let done = x.completed * CHART_WIDTH / scale;
+            let open = (x.created * CHART_WIDTH / scale).saturating_sub(done);
+            
+            try!(writeln!(f, "{}-{:02}-{:02} : {:>4} : {:>4} : {}{}", 
+                          1900 + tm.tm_year, 1 + tm.tm_mon, tm.tm_mday,
+                          x.created.saturating_sub(x.completed), x.completed,
+                          "#".repeat(done), "-".repeat(open)));
+        }
+        
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use task::{Task, TaskList, State};
+    use timestamp::{Timestamp, Duration, Clock};
+    use daterange::DateRange;
+    use tz;
+    use tz::TimeZone;
+    
+    fn at(day: i64) -> Timestamp {
+        Timestamp::from_seconds(day * 86400 + 43200)
+    }
+    
+    /* created, deadline and completed on the given days */
+    fn tasks() -> TaskList {
+        let mut tasks = TaskList::new();
+        let now = Clock::Fixed(at(10));
+        
+        for &(created, deadline, completed) in &[(0, 5, Some(2)), 
+                                                 (1, 3, Some(4)),
+                                                 (2, 20, None),
+                                                 (4, 6, None)] {
+            let mut task = Task::new(&Clock::Fixed(at(created)));
+            task.force_deadline(at(deadline));
+            
+            if let Some(x) = completed {
+                task.set_completed(at(x), &now).unwrap();
+            }
+            
+            tasks.add(task);
+        }
+        
+        tasks
+    }
+    
+    #[test]
+    fn summary() {
+        let tasks = tasks();
+        let clock = Clock::Fixed(at(10));
+        let stats = Stats::new(tasks.iter().collect(), &clock, 
+                               DateRange::unbounded());
+        
+        assert_eq!(stats.count(State::Pending), 1);
+        assert_eq!(stats.count(State::Missed), 2);
+        assert_eq!(stats.count(State::Completed), 1);
+        assert_eq!(stats.completed().len(), 2);
+        assert_eq!(stats.late(), 1);
+        assert_eq!(stats.lead_time(), 
+                   Some(Duration::days(2) + Duration::hours(12)));
+        
+        let empty = Stats::new(vec![], &clock, DateRange::unbounded());
+        assert_eq!(empty.lead_time(), None);
+        assert!(empty.burnup().is_empty());
+        assert_eq!(format!("{}", empty), "tasks     : 0 -- 0 pending, \
+                                          0 waiting, 0 missed, 0 completed\n");
+    }
+    
+    #[test]
+    fn burnup() {
+        tz::set_default(TimeZone::utc());
+        
+        let tasks = tasks();
+        let clock = Clock::Fixed(at(10));
+        let stats = Stats::new(tasks.iter().collect(), &clock, 
+                               DateRange::unbounded());
+        let points = stats.burnup();
+        
+        assert_eq!(points.len(), 11);
+        assert_eq!(points[0], Point { day: 0, created: 1, completed: 0 });
+        assert_eq!(points[2], Point { day: 2, created: 3, completed: 1 });
+        assert_eq!(points[10], Point { day: 10, created: 4, completed: 2 });
+        
+        let range = DateRange::new(at(3), at(5));
+        let stats = Stats::new(tasks.iter().collect(), &clock, range);
+        let points = stats.burnup();
+        
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].day, 3);
+        assert_eq!(points[2], Point { day: 5, created: 4, completed: 2 });
+        
+        let s = format!("{}", stats);
+        assert!(s.contains("completed : 2 of 4 (50%), 1 late (50%)\n"));
+        assert!(s.contains("1970-01-04 :    2 :    1 : ##########----------"));
+    }
+    
+    #[test]
+    fn completed_before_created() {
+        tz::set_default(TimeZone::utc());
+        
+        let clock = Clock::Fixed(at(10));
+        let mut task = Task::new(&Clock::Fixed(at(5)));
+        task.set_completed(at(3), &clock).unwrap();
+        
+        let stats = Stats::new(vec![&task], &clock, DateRange::unbounded());
+        
+        assert_eq!(stats.lead_time(), Some(Duration::zero()));
+        assert!(stats.burnup().iter().all(|x| x.completed <= x.created));
+        assert!(format!("{}", stats).contains("completed : 1 of 1 (100%)"));
+    }
+}
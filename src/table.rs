@@ -0,0 +1,347 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+
+use std::cmp::max;
+
+use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
+
+use task::{Task, TaskList};
+use timestamp::Clock;
+use error::Error;
+use color;
+
+/* descriptions never get narrower than this, even on tiny terminals */
+const MIN_DESCRIPTION: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Column {
+    Id,
+    State,
+    Priority,
+    Due,
+    Age,
+    Project,
+    Tags,
+    Description,
+}
+
+/* 
+ * Lays out tasks in aligned columns. The description takes the space
+ * left over by the other columns and is wrapped or truncated to fit.
+ */
+pub struct Table {
+    pub columns: Vec<Column>,
+    /* unlimited if not set */
+    pub width: Option<usize>,
+    pub wrap: bool,
+}
+
+impl Column {
+    /* e.g. "id,due,description" */
+    pub fn from_string(s: &str) -> Result<Vec<Column>, Error> {
+        s.split(',').map(|x| {
+            match x.trim() {
+                "id" => Ok(Column::Id),
+                "state" | "status" => Ok(Column::State),
+                "pri" | "priority" => Ok(Column::Priority),
+                "due" | "deadline" => Ok(Column::Due),
+                "age" => Ok(Column::Age),
+                "project" => Ok(Column::Project),
+                "tags" => Ok(Column::Tags),
+                "description" | "text" => Ok(Column::Description),
+                _ => Err(Error::Usage(format!("invalid column \"{}\" - \
+                                               expected id, state, priority, \
+                                               due, age, project, tags or \
+                                               description", x))),
+            }
+        }).collect()
+    }
+    
+    fn header(&self) -> &'static str {
+        match *self {
+            Column::Id => "ID",
+            Column::State => "State",
+            Column::Priority => "Pri",
+            Column::Due => "Due",
+            Column::Age => "Age",
+            Column::Project => "Project",
+            Column::Tags => "Tags",
+            Column::Description => "Description",
+        }
+    }
+    
+    fn cell(&self, i: usize, task: &Task, clock: &Clock) -> String {
+        let now = clock.now();
+        
+        match *self {
+            Column::Id => format!("{}", i + 1),
+            Column::State => format!("{}", task.state(clock)),
+            Column::Priority => task.priority().map_or(String::new(), |x| {
+                x.to_string()
+            }),
+            Column::Due if task.deadline().valid() => {
+                task.deadline().styled(&now, "overdue")
+            },
+            Column::Due => String::new(),
+            Column::Age => {
+                /* only the largest unit, e.g. "3d" */
+                let age = format!("{}", now - task.created());
+                age.split(' ').next().unwrap_or("").to_string()
+            },
+            Column::Project => task.project().unwrap_or("").to_string(),
+            Column::Tags => task.tags().join(","),
+            Column::Description => task.text().clone(),
+        }
+    }
+}
+
+/* pads to the given display width, wide characters count twice */
+fn pad(s: &str, width: usize, right: bool) -> String {
+    let fill = " ".repeat(width.saturating_sub(s.width()));
+    
+    if right { fill + s } else { format!("{}{}", s, fill) }
+}
+
+fn truncate(s: &str, width: usize) -> String {
+    if s.width() <= width {
+        return s.to_string();
+    }
+    
+    let mut result = String::new();
+    let mut used = 0;
+    
+    /* leave room for the ellipsis */
+    for x in s.chars() {
+        let w = x.width().unwrap_or(0);
+        
+        if used + w + 1 > width {
+            break;
+        }
+        
+        result.push(x);
+        used += w;
+    }
+    
+    result.push('…');
+    result
+}
+
+/* breaks at spaces, words longer than a line are split */
+fn wrap(s: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    
+    for word in s.split_whitespace() {
+        let mut word = word.to_string();
+        
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        
+        while word.width() > width {
+            let mut head = String::new();
+            
+            /* at least one character, even if it is wider than a line */
+            for x in word.chars() {
+                if !head.is_empty() 
+                    && head.width() + x.width().unwrap_or(0) > width {
+                    break;
+                }
+                
+                head.push(x);
+            }
+            
+            word = word[head.len()..].to_string();
+            lines.push(head);
+        }
+        
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        
+        line.push_str(&word);
+    }
+    
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    
+    lines
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Table {
+        Table { columns: columns, width: None, wrap: false }
+    }
+    
+    /* the header followed by the given tasks, colored by their state */
+    pub fn render(&self, tasks: &TaskList, v: &[usize], clock: &Clock) 
+                  -> String {
+        let clock = clock.frozen();
+        
+        let rows: Vec<Vec<String>> = v.iter().map(|&i| {
+            self.columns.iter().map(|x| x.cell(i, &tasks[i], &clock)).collect()
+        }).collect();
+        
+        let mut widths: Vec<usize> = self.columns.iter()
+            .map(|x| x.header().width())
+            .collect();
+        
+        for row in &rows {
+            for (j, x) in row.iter().enumerate() {
+                widths[j] = max(widths[j], x.width());
+            }
+        }
+        
+        /* only the description gives way to the width of the terminal */
+        if let (Some(width), Some(j)) = (self.width, self.description()) {
+            let others: usize = widths.iter().enumerate()
+                .filter(|&(k, _)| k != j)
+                .map(|(_, x)| x + 1)
+                .sum();
+            
+            widths[j] = max(MIN_DESCRIPTION, width.saturating_sub(others))
+                .min(widths[j]);
+        }
+        
+        let header: Vec<String> = self.columns.iter().enumerate()
+            .map(|(j, x)| truncate(x.header(), widths[j]))
+            .collect();
+        let cells: Vec<&str> = header.iter().map(|x| x.as_ref()).collect();
+        let mut s = self.line(&cells, &widths);
+        
+        for (row, &i) in rows.iter().zip(v) {
+            let state = tasks[i].state(&clock);
+            
+            for x in self.split(row, &widths) {
+                let cells: Vec<&str> = x.iter().map(|y| y.as_ref()).collect();
+                let line = self.line(&cells, &widths);
+                
+                s.push_str(&color::paint(state, line.trim_right_matches('\n')));
+                s.push('\n');
+            }
+        }
+        
+        s
+    }
+    
+    fn description(&self) -> Option<usize> {
+        self.columns.iter().position(|x| *x == Column::Description)
+    }
+    
+    /* one row becomes several lines if its description is wrapped */
+    fn split(&self, row: &[String], widths: &[usize]) -> Vec<Vec<String>> {
+        let j = match self.description() {
+            Some(j) if row[j].width() > widths[j] => j,
+            _ => return vec![row.to_vec()],
+        };
+        
+        if !self.wrap {
+            let mut row = row.to_vec();
+            row[j] = truncate(&row[j], widths[j]);
+            return vec![row];
+        }
+        
+        wrap(&row[j], widths[j]).into_iter().enumerate().map(|(k, x)| {
+            let mut line: Vec<String> = if k == 0 {
+                row.to_vec()
+            } else {
+                vec![String::new(); row.len()]
+            };
+            
+            line[j] = x;
+            line
+        }).collect()
+    }
+    
+    fn line(&self, cells: &[&str], widths: &[usize]) -> String {
+        let v: Vec<String> = cells.iter().enumerate().map(|(j, x)| {
+            pad(x, widths[j], self.columns[j] == Column::Id)
+        }).collect();
+        
+        format!("{}\n", v.join(" ").trim_right())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::{truncate, wrap};
+    use task::{Task, TaskList};
+    use timestamp::{Timestamp, Clock};
+    use color;
+    
+    fn render(text: &str, columns: &str, width: Option<usize>, wrap: bool) 
+              -> String {
+        let clock = Clock::Fixed(Timestamp::from_seconds(1000 + 3 * 86400));
+        let mut tasks = TaskList::new();
+        
+        let mut task = Task::new(&Clock::Fixed(Timestamp::from_seconds(1000)));
+        task.set_text(text);
+        task.add_tag("a");
+        task.add_tag("b");
+        tasks.add(task);
+        
+        let mut table = Table::new(Column::from_string(columns).unwrap());
+        table.width = width;
+        table.wrap = wrap;
+        
+        color::set_default(None);
+        table.render(&tasks, &[0], &clock)
+    }
+    
+    #[test]
+    fn columns() {
+        assert_eq!(Column::from_string("id, due,text").unwrap(), 
+                   vec![Column::Id, Column::Due, Column::Description]);
+        assert!(Column::from_string("id,size").is_err());
+        
+        assert_eq!(render("write docs", "id,state,age,tags,description", 
+                          None, false),
+                   "ID State   Age Tags Description\n \
+                    1 pending 3d  a,b  write docs\n");
+    }
+    
+    #[test]
+    fn widths() {
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("日本語テキスト", 7), "日本語…");
+        assert_eq!(truncate("abc", 4), "abc");
+        
+        assert_eq!(wrap("write the docs", 9), vec!["write the", "docs"]);
+        assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(wrap("日本語", 4), vec!["日本", "語"]);
+        assert_eq!(wrap("日本", 1), vec!["日", "本"]);
+        assert_eq!(wrap("ab", 0), vec!["a", "b"]);
+        
+        assert_eq!(render("write the docs", "id,description", Some(12), false),
+                   "ID Descripti…\n 1 write the…\n");
+        assert_eq!(render("write the docs", "id,description", Some(12), true),
+                   "ID Descripti…\n 1 write the\n   docs\n");
+    }
+}
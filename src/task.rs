@@ -24,45 +24,147 @@
 
 
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Write, Seek, SeekFrom};
-use std::io::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::io::ErrorKind;
 use std::vec::Vec;
 use std::fmt::{Display, Formatter};
 use std::fmt;
 use std::ops::{Index, IndexMut};
+use std::slice::Iter;
 
 use rustc_serialize::json;
 
-use timestamp::Timestamp;
+use timestamp::{Timestamp, Duration, Clock};
+use error::Error;
+use color;
 
 
-#[derive(RustcDecodable, RustcEncodable)]
+#[derive(RustcDecodable, RustcEncodable, Clone, Copy, PartialEq, Eq, 
+         PartialOrd, Ord, Debug)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    Pending,
+    Waiting,
+    Missed,
+    Completed,
+}
+
+#[derive(RustcDecodable, RustcEncodable, Clone)]
 pub struct Task {
     created: Timestamp,
     deadline: Timestamp,
     completed: Timestamp,
+    /* missing in files written by older versions */
+    scheduled: Option<Timestamp>,
+    wait: Option<Timestamp>,
+    estimate: Option<Duration>,
+    tracked: Option<Duration>,
+    reminders: Option<Vec<Duration>>,
+    /* reminders that have already been delivered for the current deadline */
+    reminded: Option<Vec<Duration>>,
+    priority: Option<Priority>,
+    project: Option<String>,
+    tags: Option<Vec<String>>,
 
     text: String,
 }
 
-#[derive(RustcDecodable, RustcEncodable)]
+#[derive(RustcDecodable, RustcEncodable, Clone)]
 pub struct TaskList {
     tasks: Vec<Task>,
 }
 
 pub struct TaskFile {
     file: File,
+    path: PathBuf,
 }
 
+pub struct TaskDisplay<'a> {
+    task: &'a Task,
+    now: Timestamp,
+}
 
-impl Task {
-    pub fn new() -> Task {
-        let now = Timestamp::now();
+pub struct TaskListDisplay<'a> {
+    tasks: &'a TaskList,
+    clock: Clock,
+    indices: Vec<usize>,
+}
+
+
+impl Priority {
+    pub fn from_string(s: &str) -> Result<Priority, Error> {
+        match s.to_lowercase().as_ref() {
+            "l" | "low" => Ok(Priority::Low),
+            "m" | "medium" => Ok(Priority::Medium),
+            "h" | "high" => Ok(Priority::High),
+            _ => Err(Error::Usage(format!("invalid priority \"{}\" - \
+                                           expected H, M or L", s))),
+        }
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match *self {
+            Priority::Low => "L",
+            Priority::Medium => "M",
+            Priority::High => "H",
+        };
+        
+        f.pad(s)
+    }
+}
+
+impl State {
+    pub fn from_string(s: &str) -> Result<State, Error> {
+        match s {
+            "pending" => Ok(State::Pending),
+            "waiting" => Ok(State::Waiting),
+            "missed" => Ok(State::Missed),
+            "completed" => Ok(State::Completed),
+            _ => Err(Error::Usage(format!("invalid state \"{}\" - expected \
+                                           pending, waiting, missed or \
+                                           completed", s))),
+        }
+    }
+}
+
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match *self {
+            State::Pending => "pending",
+            State::Waiting => "waiting",
+            State::Missed => "missed",
+            State::Completed => "completed",
+        };
         
+        f.pad(s)
+    }
+}
+
+impl Task {
+    pub fn new(clock: &Clock) -> Task {
         Task { 
-            created: now,
+            created: clock.now(),
             deadline: Timestamp::new(),
             completed: Timestamp::new(),
+            scheduled: None,
+            wait: None,
+            estimate: None,
+            tracked: None,
+            reminders: None,
+            reminded: None,
+            priority: None,
+            project: None,
+            tags: None,
             text: "".to_string(),
         }
     }
@@ -75,52 +177,259 @@ impl Task {
         &self.text
     }
     
-    pub fn set_completed(&mut self, ts: Timestamp) {
-        if ts <= Timestamp::now() {
-            self.completed = ts;
+    pub fn created(&self) -> Timestamp {
+        self.created
+    }
+    
+    pub fn deadline(&self) -> Timestamp {
+        self.deadline
+    }
+    
+    pub fn completed(&self) -> Timestamp {
+        self.completed
+    }
+    
+    pub fn set_completed(&mut self, ts: Timestamp, clock: &Clock) 
+                         -> Result<(), Error> {
+        if ts > clock.now() {
+            return Err(Error::Usage(format!("completion time {} lies in the \
+                                             future", ts)));
         }
+        
+        self.completed = ts;
+        
+        Ok(())
+    }
+    
+    /* 
+     * Deadlines are given in whole seconds, a completion within the second
+     * of the deadline is still in time.
+     */
+    pub fn completed_late(&self) -> bool {
+        self.completed.valid() && self.deadline.valid()
+            && self.completed.seconds() > self.deadline.seconds()
     }
     
     pub fn is_completed(&self) -> bool {
-        self.completed.valid() && self.completed <= self.deadline
+        self.completed.valid() && !self.completed_late()
     }
     
-    pub fn deadline_missed(&self) -> bool {
-        let ts = Timestamp::now();
+    pub fn deadline_missed(&self, clock: &Clock) -> bool {
+        let ts = clock.now();
         
         if self.completed.valid() {
-            self.completed > self.deadline
+            self.completed_late()
         } else {
             self.deadline.valid() && ts > self.deadline
         }
     }
     
-    pub fn set_deadline(&mut self, ts: Timestamp) {
-        if ts >= Timestamp::now() {
-            self.deadline = ts;
+    pub fn set_deadline(&mut self, ts: Timestamp, clock: &Clock) 
+                        -> Result<(), Error> {
+        if ts < clock.now() {
+            return Err(Error::Usage(format!("deadline {} lies in the past", 
+                                            ts)));
+        }
+        
+        self.force_deadline(ts);
+        
+        Ok(())
+    }
+    
+    /* accepts deadlines in the past, e.g. when recording historical data */
+    pub fn force_deadline(&mut self, ts: Timestamp) {
+        if ts != self.deadline {
+            self.reminded = None;
+        }
+        
+        self.deadline = ts;
+    }
+    
+    pub fn set_scheduled(&mut self, ts: Timestamp) {
+        self.scheduled = Some(ts);
+    }
+    
+    pub fn scheduled(&self) -> Option<Timestamp> {
+        self.scheduled
+    }
+    
+    pub fn set_wait(&mut self, ts: Timestamp) {
+        self.wait = Some(ts);
+    }
+    
+    pub fn wait(&self) -> Option<Timestamp> {
+        self.wait
+    }
+    
+    /* the point in time from which on the task can be worked on */
+    pub fn actionable_from(&self) -> Option<Timestamp> {
+        match (self.scheduled, self.wait) {
+            (Some(a), Some(b)) => Some(if a > b { a } else { b }),
+            (a, b) => a.or(b),
         }
     }
+    
+    pub fn is_waiting(&self, clock: &Clock) -> bool {
+        let now = clock.now();
+        
+        !self.is_completed() && self.actionable_from().map_or(false, |x| x > now)
+    }
+    
+    pub fn set_estimate(&mut self, d: Duration) {
+        self.estimate = Some(d);
+    }
+    
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate
+    }
+    
+    pub fn track(&mut self, d: Duration) {
+        self.tracked = Some(self.tracked() + d);
+    }
+    
+    pub fn tracked(&self) -> Duration {
+        self.tracked.unwrap_or(Duration::zero())
+    }
+    
+    /* estimated work that has not been tracked yet */
+    pub fn remaining(&self) -> Duration {
+        match self.estimate {
+            Some(d) if d > self.tracked() => d - self.tracked(),
+            _ => Duration::zero(),
+        }
+    }
+    
+    /* reminds "d" before the deadline */
+    pub fn add_reminder(&mut self, d: Duration) {
+        let mut v = self.reminders();
+        v.push(d);
+        v.sort_by(|a, b| b.cmp(a));
+        v.dedup();
+        
+        self.reminders = Some(v);
+    }
+    
+    pub fn reminders(&self) -> Vec<Duration> {
+        self.reminders.clone().unwrap_or(vec![])
+    }
+    
+    pub fn reminded(&self) -> Vec<Duration> {
+        self.reminded.clone().unwrap_or(vec![])
+    }
+    
+    /* reminders that have not been delivered yet */
+    pub fn pending_reminders(&self) -> Vec<Duration> {
+        /* also tasks completed after their deadline */
+        if self.completed.valid() || !self.deadline.valid() {
+            return vec![];
+        }
+        
+        let reminded = self.reminded.clone().unwrap_or(vec![]);
+        
+        self.reminders().into_iter()
+            .filter(|x| !reminded.contains(x))
+            .collect()
+    }
+    
+    pub fn due_reminders(&self, clock: &Clock) -> Vec<Duration> {
+        let now = clock.now();
+        
+        self.pending_reminders().into_iter()
+            .filter(|x| self.deadline - *x <= now)
+            .collect()
+    }
+    
+    pub fn set_reminded(&mut self, d: Duration) {
+        let mut v = self.reminded.take().unwrap_or(vec![]);
+        v.push(d);
+        
+        self.reminded = Some(v);
+    }
+    
+    pub fn set_priority(&mut self, priority: Priority) {
+        self.priority = Some(priority);
+    }
+    
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+    
+    pub fn set_project(&mut self, project: &str) {
+        self.project = Some(project.to_string());
+    }
+    
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_ref().map(|x| x.as_ref())
+    }
+    
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.get_or_insert(vec![]).push(tag.to_string());
+        }
+    }
+    
+    pub fn remove_tag(&mut self, tag: &str) {
+        if let Some(ref mut v) = self.tags {
+            v.retain(|x| x != tag);
+        }
+    }
+    
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|x| x == tag)
+    }
+    
+    pub fn tags(&self) -> &[String] {
+        self.tags.as_ref().map_or(&[], |x| x.as_ref())
+    }
+    
+    pub fn state(&self, clock: &Clock) -> State {
+        if self.is_completed() {
+            State::Completed
+        } else if self.deadline_missed(clock) {
+            State::Missed
+        } else if self.is_waiting(clock) {
+            State::Waiting
+        } else {
+            State::Pending
+        }
+    }
+    
+    pub fn display(&self, clock: &Clock) -> TaskDisplay {
+        TaskDisplay { task: self, now: clock.now() }
+    }
 }
 
 
-impl Display for Task {
+impl<'a> Display for TaskDisplay<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-    
-        let fmt : &str;
-        let ts : &Timestamp;
-
-        if self.is_completed() {
-            fmt = "[x] : completed at    ";
-            ts = &self.completed;
-        } else if self.deadline_missed() {
-            fmt = "[ ] : deadline missed ";
-            ts = &self.deadline;
-        } else {
-            fmt = "[ ] : deadline        ";
-            ts = &self.deadline;
+        let task = self.task;
+        let clock = Clock::Fixed(self.now);
+        
+        let (fmt, ts, past) = match task.state(&clock) {
+            State::Completed => ("[x] : completed at    ", task.completed, "ago"),
+            State::Missed => ("[ ] : deadline missed ", task.deadline, "overdue"),
+            State::Waiting => {
+                ("[ ] : waiting until   ", task.actionable_from().unwrap(), "ago")
+            },
+            State::Pending => ("[ ] : deadline        ", task.deadline, "ago"),
+        };
+        
+        try!(write!(f, "{} -- {:<22} -- \"{}\"", 
+                    fmt, ts.styled(&self.now, past), task.text));
+        
+        if let Some(x) = task.priority {
+            try!(write!(f, " pri:{}", x));
+        }
+        
+        if let Some(ref x) = task.project {
+            try!(write!(f, " project:{}", x));
+        }
+        
+        for x in task.tags() {
+            try!(write!(f, " +{}", x));
         }
         
-        write!(f, "{} -- {} -- \"{}\"", fmt, ts, self.text)
+        Ok(())
     }
 }
 
@@ -133,29 +442,105 @@ impl TaskList {
         self.tasks.push(task);
     }
     
-    pub fn remove(&mut self, i: usize) {
-        self.tasks.remove(i);
+    fn check(&self, i: usize) -> Result<(), Error> {
+        if i >= self.tasks.len() {
+            return Err(Error::NotFound(format!("task {} does not exist - \
+                                                there are {} task(s)", 
+                                               i + 1, self.tasks.len())));
+        }
+        
+        Ok(())
+    }
+    
+    pub fn get(&self, i: usize) -> Result<&Task, Error> {
+        try!(self.check(i));
+        
+        Ok(&self.tasks[i])
+    }
+    
+    pub fn get_mut(&mut self, i: usize) -> Result<&mut Task, Error> {
+        try!(self.check(i));
+        
+        Ok(&mut self.tasks[i])
+    }
+    
+    pub fn remove(&mut self, i: usize) -> Result<Task, Error> {
+        try!(self.check(i));
+        
+        Ok(self.tasks.remove(i))
+    }
+    
+    /* removes either all of the tasks or none of them */
+    pub fn remove_many(&mut self, v: &[usize]) -> Result<(), Error> {
+        for &i in v {
+            try!(self.check(i));
+        }
+        
+        let mut v = v.to_vec();
+        v.sort_by(|a, b| b.cmp(a));
+        v.dedup();
+        
+        for i in v {
+            self.tasks.remove(i);
+        }
+        
+        Ok(())
     }
     
     pub fn remove_all(&mut self) {
         self.tasks.clear();
     }
     
-    pub fn complete(&mut self, i: usize) {
-        if !self.tasks[i].is_completed() {
-            self.tasks[i].set_completed(Timestamp::now());
-        }
+    pub fn complete(&mut self, i: usize, ts: Timestamp, clock: &Clock) 
+                    -> Result<(), Error> {
+        self.complete_many(&[i], ts, clock)
     }
     
-    pub fn complete_all(&mut self) {
-        for i in 0..self.tasks.len() {
-            self.complete(i);
+    /* completes either all of the tasks or none of them */
+    pub fn complete_many(&mut self, v: &[usize], ts: Timestamp, clock: &Clock) 
+                         -> Result<(), Error> {
+        for &i in v {
+            try!(self.check(i));
+        }
+        
+        if ts > clock.now() {
+            return Err(Error::Usage(format!("completion time {} lies in the \
+                                             future", ts)));
         }
+        
+        for &i in v {
+            if !self.tasks[i].is_completed() {
+                try!(self.tasks[i].set_completed(ts, clock));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    pub fn complete_all(&mut self, ts: Timestamp, clock: &Clock) 
+                        -> Result<(), Error> {
+        let v: Vec<usize> = (0..self.tasks.len()).collect();
+        
+        self.complete_many(&v, ts, clock)
+    }
+    
+    /* lists the given tasks in the given order */
+    pub fn display(&self, clock: &Clock, indices: Vec<usize>) 
+                   -> TaskListDisplay {
+        TaskListDisplay { tasks: self, clock: *clock, indices: indices }
+    }
+    
+    pub fn waiting(&self, clock: &Clock) -> usize {
+        self.tasks.iter().filter(|x| x.is_waiting(clock)).count()
     }
     
     pub fn len(&self) -> usize {
         self.tasks.len()
     }
+    
+    pub fn iter(&self) -> Iter<Task> {
+        self.tasks.iter()
+    }
 }
 
 impl Index<usize> for TaskList {
@@ -172,26 +557,15 @@ impl IndexMut<usize> for TaskList {
     }
 }
 
-impl Display for TaskList {
+impl<'a> Display for TaskListDisplay<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        static RED: &'static str = "\x1B[1;31m";
-        static GREEN: &'static str =  "\x1B[1;32m";
-        static YELLOW: &'static str = "\x1B[1;33m";
-        static DEFAULT: &'static str = "\x1B[0m";
+        let clock = self.clock.frozen();
         
-        for i in 0..self.tasks.len() {
+        for &i in &self.indices {
             let task = &self.tasks[i];
-            let color: &str;
-            
-            if task.is_completed() {
-                color = GREEN;
-            } else if task.deadline_missed() {
-                color = RED;
-            } else {
-                color = YELLOW;
-            }
+            let line = format!(" {:2} : {}", i + 1, task.display(&clock));
         
-            try!(writeln!(f, "{} {:2} : {}{}", color, i + 1, task, DEFAULT));
+            try!(writeln!(f, "{}", color::paint(task.state(&clock), &line)));
         }
         
         Ok(())
@@ -206,28 +580,401 @@ impl TaskFile {
             .write(true)
             .create(true);
         
-        let file = try!(open_opts.open(path));
+        let file = try!(open_opts.open(path).map_err(|e| Error::io(path, e)));
         
-        Ok(TaskFile { file: file })
+        Ok(TaskFile { file: file, path: PathBuf::from(path) })
+    }
+    
+    fn name(&self) -> String {
+        self.path.to_string_lossy().into_owned()
     }
     
-    pub fn load(&mut self) -> TaskList {
+    pub fn load(&mut self) -> Result<TaskList, Error> {
         let mut s = String::new();
         
-        self.file.read_to_string(&mut s).unwrap();
+        try!(self.file.read_to_string(&mut s)
+             .map_err(|e| Error::io(&self.name(), e)));
+        
+        /* a new task file is empty */
+        if s.trim().is_empty() {
+            return Ok(TaskList::new());
+        }
+        
+        json::decode(&s).map_err(|_| {
+            Error::Corrupt(format!("\"{}\" is not a valid task file", 
+                                   self.name()))
+        })
+    }
+    
+    /* 
+     * Writes the tasks to a temporary file first and replaces the task file
+     * with it, so the task file is never left half written. Symbolic links
+     * are followed and the permissions of the task file are kept.
+     */
+    pub fn save(&mut self, tasks: &TaskList) -> Result<(), Error> {
+        let path = try!(fs::canonicalize(&self.path)
+                        .map_err(|e| Error::io(&self.name(), e)));
+        let perms = try!(fs::metadata(&path)
+                         .map_err(|e| Error::io(&self.name(), e)))
+            .permissions();
+        
+        /* concurrent writers must not share a temporary file */
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", ::std::process::id()));
+        
+        let data = try!(json::encode(tasks).map_err(|e| {
+            Error::io(&self.name(), io::Error::new(ErrorKind::Other, e))
+        }));
+        
+        let tmp_name = tmp.to_string_lossy().into_owned();
+        let mut file = try!(File::create(&tmp)
+                            .map_err(|e| Error::io(&tmp_name, e)));
+        
+        try!(file.set_permissions(perms)
+             .and_then(|_| file.write_all(data.as_bytes()))
+             .and_then(|_| file.sync_all())
+             .map_err(|e| Error::io(&tmp_name, e)));
+        
+        fs::rename(&tmp, &path).map_err(|e| Error::io(&self.name(), e))
+    }
+}
+
 
-        json::decode(&s).unwrap_or(TaskList::new())
+/* helpers for the tests of all modules */
+#[cfg(test)]
+pub mod fixture {
+    use task::Task;
+    use timestamp::{Timestamp, Clock};
+    
+    /* friday, 2024-03-01 10:00 UTC */
+    pub static NOW: i64 = 1709287200;
+    
+    pub fn clock(seconds: i64) -> Clock {
+        Clock::Fixed(Timestamp::from_seconds(seconds))
     }
     
-    pub fn save(&mut self, tasks: &TaskList) {
-        json::encode(tasks).map(|x| {
-            self.file.seek(SeekFrom::Start(0)).unwrap();
-            self.file.set_len(0).unwrap();
-            
-            let data = x.into_bytes();
-            self.file.write_all(&data).unwrap();
-        }).unwrap();
+    /* a task created at 1000 seconds after the epoch */
+    pub fn task(text: &str, deadline: i64) -> Task {
+        let mut task = Task::new(&clock(1000));
+        task.set_text(text);
+        task.force_deadline(Timestamp::from_seconds(deadline));
+        
+        task
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fixture::{clock, task};
+    use rustc_serialize::json;
+    use timestamp::{Timestamp, Clock};
 
+    #[test]
+    fn new_task() {
+        let task = Task::new(&clock(1000));
+        
+        assert_eq!(task.created, Timestamp::from_seconds(1000));
+        assert!(!task.deadline.valid());
+        assert!(!task.is_completed());
+        assert!(!task.deadline_missed(&clock(1000000)));
+        assert!(task.text().is_empty());
+    }
+    
+    #[test]
+    fn deadline() {
+        let task = task("x", 2000);
+        
+        assert_eq!(task.deadline, Timestamp::from_seconds(2000));
+        assert!(!task.deadline_missed(&clock(2000)));
+        assert!(task.deadline_missed(&clock(2001)));
+    }
+    
+    #[test]
+    fn past_deadline() {
+        let mut task = Task::new(&clock(1000));
+        
+        assert!(task.set_deadline(Timestamp::from_seconds(999), &clock(1000))
+                .is_err());
+        assert!(!task.deadline.valid());
+        
+        assert!(task.set_deadline(Timestamp::from_seconds(1000), &clock(1000))
+                .is_ok());
+        
+        task.force_deadline(Timestamp::from_seconds(500));
+        assert_eq!(task.deadline, Timestamp::from_seconds(500));
+        assert!(task.deadline_missed(&clock(1000)));
+    }
+    
+    #[test]
+    fn completion() {
+        let mut task = task("x", 2000);
+        
+        assert!(task.set_completed(Timestamp::from_seconds(3000), &clock(1500))
+                .is_err());
+        assert!(!task.is_completed());
+        
+        assert!(task.set_completed(Timestamp::from_seconds(1200), &clock(1500))
+                .is_ok());
+        assert!(task.is_completed());
+        assert!(!task.deadline_missed(&clock(5000)));
+    }
+    
+    #[test]
+    fn late_completion() {
+        let mut task = task("x", 2000);
+        
+        task.set_completed(Timestamp::from_seconds(2500), &clock(2500)).unwrap();
+        assert!(!task.is_completed());
+        assert!(task.deadline_missed(&clock(2500)));
+        
+        /* completed within the second of the deadline */
+        let ts = ::time::Timespec::new(2000, 500000000);
+        let ts = Timestamp::from_timespec(&ts);
+        
+        task.set_completed(ts, &clock(2500)).unwrap();
+        assert!(ts > task.deadline());
+        assert!(!task.completed_late());
+        assert!(task.is_completed());
+        assert!(!task.deadline_missed(&clock(2500)));
+    }
+    
+    #[test]
+    fn waiting() {
+        let mut task = task("x", 5000);
+        
+        assert!(task.actionable_from().is_none());
+        assert!(!task.is_waiting(&clock(1000)));
+        
+        task.set_wait(Timestamp::from_seconds(2000));
+        task.set_scheduled(Timestamp::from_seconds(3000));
+        
+        assert_eq!(task.actionable_from(), Some(Timestamp::from_seconds(3000)));
+        assert!(task.is_waiting(&clock(2500)));
+        assert!(!task.is_waiting(&clock(3000)));
+        
+        task.set_completed(Timestamp::from_seconds(1500), &clock(1500)).unwrap();
+        assert!(!task.is_waiting(&clock(2500)));
+    }
+    
+    #[test]
+    fn hidden_tasks() {
+        let mut tasks = TaskList::new();
+        let mut waiting = task("later", 5000);
+        waiting.set_wait(Timestamp::from_seconds(3000));
+        
+        tasks.add(task("now", 5000));
+        tasks.add(waiting);
+        
+        let clock = clock(2000);
+        let shown = format!("{}", tasks.display(&clock, vec![0]));
+        let all = format!("{}", tasks.display(&clock, vec![1, 0]));
+        
+        assert_eq!(tasks.waiting(&clock), 1);
+        assert_eq!(tasks[1].state(&clock), State::Waiting);
+        assert!(shown.contains("\"now\"") && !shown.contains("\"later\""));
+        assert!(all.find("\"later\"") < all.find("\"now\""));
+        assert!(all.contains(" 2 : "));
+    }
+    
+    #[test]
+    fn estimates() {
+        let mut task = task("x", 5000);
+        
+        assert_eq!(task.remaining(), Duration::zero());
+        
+        task.set_estimate(Duration::hours(2));
+        task.track(Duration::minutes(30));
+        task.track(Duration::minutes(60));
+        
+        assert_eq!(task.tracked(), Duration::minutes(90));
+        assert_eq!(task.remaining(), Duration::minutes(30));
+        
+        task.track(Duration::hours(1));
+        assert_eq!(task.remaining(), Duration::zero());
+    }
+    
+    #[test]
+    fn reminders() {
+        let mut task = task("x", 10000);
+        
+        task.add_reminder(Duration::minutes(10));
+        task.add_reminder(Duration::hours(1));
+        task.add_reminder(Duration::minutes(10));
+        
+        assert_eq!(task.reminders(), vec![Duration::hours(1), 
+                                          Duration::minutes(10)]);
+        assert!(task.due_reminders(&clock(6000)).is_empty());
+        assert_eq!(task.due_reminders(&clock(6400)), vec![Duration::hours(1)]);
+        
+        task.set_reminded(Duration::hours(1));
+        assert!(task.due_reminders(&clock(6400)).is_empty());
+        assert_eq!(task.due_reminders(&clock(9400)), 
+                   vec![Duration::minutes(10)]);
+        
+        /* a new deadline brings back all reminders */
+        task.force_deadline(Timestamp::from_seconds(20000));
+        assert_eq!(task.due_reminders(&clock(16400)), vec![Duration::hours(1)]);
+        
+        task.set_completed(Timestamp::from_seconds(16400), &clock(16400))
+            .unwrap();
+        assert!(task.due_reminders(&clock(19500)).is_empty());
+    }
+    
+    #[test]
+    fn task_list() {
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        tasks.add(task("b", 3000));
+        tasks.add(task("c", 4000));
+        
+        tasks.complete(1, Timestamp::from_seconds(1500), &clock(1500)).unwrap();
+        assert!(!tasks[0].is_completed());
+        assert!(tasks[1].is_completed());
+        assert_eq!(tasks[1].completed, Timestamp::from_seconds(1500));
+        
+        tasks.remove(0).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].text(), "b");
+        
+        tasks.complete_all(Timestamp::from_seconds(1600), &clock(1600))
+            .unwrap();
+        assert!(tasks[1].is_completed());
+        assert_eq!(tasks[0].completed, Timestamp::from_seconds(1500));
+        
+        tasks.remove_all();
+        assert_eq!(tasks.len(), 0);
+    }
+    
+    #[test]
+    fn invalid_indices() {
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        tasks.add(task("b", 3000));
+        
+        let ts = Timestamp::from_seconds(1500);
+        
+        assert!(tasks.get(2).is_err());
+        assert!(tasks.remove(2).is_err());
+        assert!(tasks.complete(2, ts, &clock(1500)).is_err());
+        
+        assert!(tasks.complete_many(&[0, 5], ts, &clock(1500)).is_err());
+        assert!(!tasks[0].is_completed());
+        
+        assert!(tasks.remove_many(&[1, 2]).is_err());
+        assert_eq!(tasks.len(), 2);
+        
+        tasks.remove_many(&[1, 0, 1]).unwrap();
+        assert_eq!(tasks.len(), 0);
+    }
+    
+    #[test]
+    fn complete_in_future() {
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        
+        assert!(tasks.complete(0, Timestamp::from_seconds(1600), &clock(1500))
+                .is_err());
+        assert!(!tasks[0].is_completed());
+    }
+    
+    #[test]
+    fn creation_order() {
+        let clock = Clock::System;
+        let a = Task::new(&clock);
+        let b = Task::new(&clock);
+        
+        assert!(a.created <= b.created);
+        assert!(a.created.nanos() > 0 || b.created.nanos() > 0);
+    }
+    
+    #[test]
+    fn legacy_format() {
+        let s = "{\"tasks\":[{\"created\":{\"seconds\":1000},\
+                 \"deadline\":{\"seconds\":2000},\
+                 \"completed\":{\"seconds\":9223372036854775807},\
+                 \"text\":\"a\"}]}";
+        
+        let tasks: TaskList = json::decode(s).unwrap();
+        
+        assert_eq!(tasks[0].created, Timestamp::from_seconds(1000));
+        assert_eq!(tasks[0].deadline, Timestamp::from_seconds(2000));
+        assert!(!tasks[0].completed.valid());
+    }
+    
+    #[test]
+    fn serialization() {
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        tasks.add(Task::new(&Clock::System));
+        tasks.complete(0, Timestamp::from_seconds(1500), &clock(1500)).unwrap();
+        
+        let s = json::encode(&tasks).unwrap();
+        let decoded: TaskList = json::decode(&s).unwrap();
+        
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[1].created, tasks[1].created);
+        assert_eq!(decoded[0].text(), "a");
+        assert_eq!(decoded[0].created, Timestamp::from_seconds(1000));
+        assert_eq!(decoded[0].deadline, Timestamp::from_seconds(2000));
+        assert_eq!(decoded[0].completed, Timestamp::from_seconds(1500));
+    }
+    
+    #[test]
+    fn task_file() {
+        let mut path = ::std::env::temp_dir();
+        path.push(format!("tap-test-{}.json", ::std::process::id()));
+        let name = path.to_string_lossy().into_owned();
+        
+        /* a new task file starts out empty */
+        let mut file = TaskFile::new(&name).unwrap();
+        assert_eq!(file.load().unwrap().len(), 0);
+        
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        file.save(&tasks).unwrap();
+        
+        assert_eq!(TaskFile::new(&name).unwrap().load().unwrap().len(), 1);
+        
+        fs::write(&path, "{ not json").unwrap();
+        
+        match TaskFile::new(&name).unwrap().load() {
+            Err(Error::Corrupt(_)) => (),
+            _ => panic!("corrupt task file accepted"),
+        }
+        
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn task_file_link() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        
+        let dir = ::std::env::temp_dir();
+        let target = dir.join(format!("tap-test-{}-target.json", 
+                                      ::std::process::id()));
+        let link = dir.join(format!("tap-test-{}-link.json", 
+                                    ::std::process::id()));
+        
+        fs::write(&target, "").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600))
+            .unwrap();
+        symlink(&target, &link).unwrap();
+        
+        let mut tasks = TaskList::new();
+        tasks.add(task("a", 2000));
+        
+        let name = link.to_string_lossy().into_owned();
+        TaskFile::new(&name).unwrap().save(&tasks).unwrap();
+        
+        /* the link still points to the saved task file */
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(TaskFile::new(&name).unwrap().load().unwrap().len(), 1);
+        
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        
+        fs::remove_file(&link).unwrap();
+        fs::remove_file(&target).unwrap();
+    }
+}
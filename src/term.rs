@@ -0,0 +1,80 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::io::{self, Write};
+use std::env;
+use std::mem;
+
+use libc;
+
+pub fn stdin_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+pub fn stdout_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/* 
+ * The number of columns of the terminal, "COLUMNS" takes precedence. 
+ * Output that does not go to a terminal has no width.
+ */
+pub fn width() -> Option<usize> {
+    let columns = env::var("COLUMNS").ok()
+        .and_then(|x| usize::from_str_radix(&x, 10).ok());
+    
+    if columns.is_some() || !stdout_is_tty() {
+        return columns;
+    }
+    
+    unsafe {
+        let mut size: libc::winsize = mem::zeroed();
+        
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0
+            && size.ws_col > 0 {
+            Some(size.ws_col as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/* 
+ * Asks a yes/no question on the terminal, anything but "y" means no. The
+ * question goes to stderr, so it never ends up in the output of a pipe.
+ */
+pub fn confirm(question: &str) -> bool {
+    let mut answer = String::new();
+
+    eprint!("{} [y/N] ", question);
+
+    if io::stderr().flush().is_err() || io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    match answer.trim().to_lowercase().as_ref() {
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
@@ -25,46 +25,330 @@
 use std::fmt;
 use std::cmp::Ordering;
 use std::cmp::PartialOrd;
-use std::ops::{Add, Sub};
+use std::ops::{Add, Sub, Mul, Neg};
+use std::iter::Sum;
 use std::i64;
+use std::cell::Cell;
+use std::env;
 
 use time;
 
-#[derive(RustcDecodable, RustcEncodable, Debug)]
+use tz;
+use tz::TimeZone;
+use calendar;
+use error::Error;
+
+#[derive(RustcDecodable, RustcEncodable, Clone, Copy, Debug)]
 pub struct Timestamp {
     seconds: i64,
+    /* missing in files written by older versions */
+    nanos: Option<u32>,
+}
+
+/* source of the current time, fixed clocks make behavior reproducible */
+#[derive(Clone, Copy, Debug)]
+pub enum Clock {
+    System,
+    Fixed(Timestamp),
+}
+
+#[derive(RustcDecodable, RustcEncodable, Clone, Copy, PartialEq, Eq, 
+         PartialOrd, Ord, Debug)]
+pub struct Duration {
+    seconds: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DateFormat {
+    Absolute,
+    Relative,
+    Both,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DateStyle {
+    pub format: DateFormat,
+    /* distances below this are shown in hours */
+    pub hours: Duration,
+    /* distances beyond this are shown as absolute dates */
+    pub limit: Duration,
+}
+
+thread_local!(static DATE_STYLE: Cell<DateStyle> = Cell::new(DateStyle::new()));
+
+/* returns the offset in seconds and the number of business days */
+fn parse_relative(s: &str) -> Result<(i64, i64), Error> {
+    let too_large = || Error::Usage(format!("time \"{}\" is out of range", s));
+    let add = |total: i64, num: i64, unit: i64| {
+        num.checked_mul(unit).and_then(|x| total.checked_add(x))
+            .ok_or(too_large())
+    };
+    
+    let mut offset = 0 as i64;
+    let mut bdays = 0 as i64;
+    let mut chars = s.chars().peekable();
+    
+    if s.is_empty() {
+        return Err(Error::Usage(format!("empty input string")));
+    }
+    
+    while chars.peek().is_some() {
+        let mut num = 0 as i64;
+        let mut unit = String::new();
+        
+        while let Some(x) = chars.peek().and_then(|x| x.to_digit(10)) {
+            num = try!(num.checked_mul(10)
+                       .and_then(|y| y.checked_add(x as i64))
+                       .ok_or(too_large()));
+            chars.next();
+        }
+        
+        while let Some(&x) = chars.peek() {
+            if x.is_digit(10) {
+                break;
+            }
+            
+            unit.push(x);
+            chars.next();
+        }
+        
+        match unit.as_ref() {
+            "y" => offset = try!(add(offset, num, 3600 * 24 * 365)),
+            "m" => offset = try!(add(offset, num, 3600 * 24 * 30)),
+            "w" => offset = try!(add(offset, num, 3600 * 24 * 7)),
+            "d" => offset = try!(add(offset, num, 3600 * 24)),
+            "h" => offset = try!(add(offset, num, 3600)),
+            "min" => offset = try!(add(offset, num, 60)),
+            "s" => offset = try!(add(offset, num, 1)),
+            "bd" => bdays = try!(add(bdays, num, 1)),
+            "" => {
+                return Err(Error::Usage(format!("missing time specifier")));
+            },
+            _ => {
+                return Err(Error::Usage(format!("invalid time specifier {}", 
+                                                unit)));
+            },
+        }
+    }
+    
+    Ok((offset, bdays))
+}
+
+impl Duration {
+    pub fn from_seconds(seconds: i64) -> Duration {
+        Duration { seconds: seconds }
+    }
+    
+    pub fn from_string(s: &str) -> Result<Duration, Error> {
+        let (offset, bdays) = try!(parse_relative(s));
+        
+        if bdays != 0 {
+            return Err(Error::Usage(format!("business days are not a fixed \
+                                             duration")));
+        }
+        
+        Ok(Duration::from_seconds(offset))
+    }
+    
+    pub fn minutes(n: i64) -> Duration {
+        Duration::from_seconds(n * 60)
+    }
+    
+    pub fn hours(n: i64) -> Duration {
+        Duration::from_seconds(n * 3600)
+    }
+    
+    pub fn days(n: i64) -> Duration {
+        Duration::from_seconds(n * 86400)
+    }
+    
+    pub fn zero() -> Duration {
+        Duration::from_seconds(0)
+    }
+    
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+    
+    pub fn abs(&self) -> Duration {
+        Duration::from_seconds(self.seconds.abs())
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+    
+    fn add(self, other: Duration) -> Duration {
+        Duration::from_seconds(self.seconds + other.seconds)
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+    
+    fn sub(self, other: Duration) -> Duration {
+        Duration::from_seconds(self.seconds - other.seconds)
+    }
+}
+
+impl Mul<i64> for Duration {
+    type Output = Duration;
+    
+    fn mul(self, n: i64) -> Duration {
+        Duration::from_seconds(self.seconds * n)
+    }
+}
+
+impl Neg for Duration {
+    type Output = Duration;
+    
+    fn neg(self) -> Duration {
+        Duration::from_seconds(-self.seconds)
+    }
+}
+
+impl Sum for Duration {
+    fn sum<I: Iterator<Item = Duration>>(iter: I) -> Duration {
+        iter.fold(Duration::zero(), |a, b| a + b)
+    }
+}
+
+/* 
+ * e.g. "2d 3h", "45min" or "-1h 30min". Positive durations parse back
+ * when unspaced, there is no syntax for negative ones. The alternate form
+ * "{:#}" uses hours as the largest unit.
+ */
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        static UNITS: [(i64, &'static str); 4] = [
+            (86400, "d"), (3600, "h"), (60, "min"), (1, "s")
+        ];
+        
+        let mut secs = self.seconds.abs();
+        let mut parts = vec![];
+        
+        if secs == 0 {
+            return f.pad("0s");
+        }
+        
+        let units = if f.alternate() { &UNITS[1..] } else { &UNITS[..] };
+        
+        for &(size, unit) in units.iter() {
+            if secs >= size {
+                parts.push(format!("{}{}", secs / size, unit));
+                secs %= size;
+            }
+        }
+        
+        let sign = if self.seconds < 0 { "-" } else { "" };
+        
+        f.pad(&format!("{}{}", sign, parts.join(" ")))
+    }
+}
+
+impl Clock {
+    /*
+     * "TAP_NOW" pins the clock to the given absolute time or, if prefixed
+     * with '@', to the given number of seconds since the epoch.
+     */
+    pub fn from_env() -> Result<Clock, Error> {
+        let s = match env::var("TAP_NOW") {
+            Ok(x) => x,
+            Err(_) => return Ok(Clock::System),
+        };
+        
+        if s.starts_with("@") {
+            return i64::from_str_radix(&s[1..], 10)
+                .map(|x| Clock::Fixed(Timestamp::from_seconds(x)))
+                .map_err(|_| {
+                    Error::Usage(format!("invalid number of seconds \"{}\"", s))
+                });
+        }
+        
+        let ts = try!(Timestamp::from_string(&s, &Clock::System));
+        
+        Ok(Clock::Fixed(ts))
+    }
+    
+    pub fn now(&self) -> Timestamp {
+        match *self {
+            Clock::System => Timestamp::from_timespec(&time::get_time()),
+            Clock::Fixed(ts) => ts,
+        }
+    }
+    
+    /* 
+     * The clock stopped at the current time, so all tasks of a listing are 
+     * evaluated at the same point in time.
+     */
+    pub fn frozen(&self) -> Clock {
+        Clock::Fixed(self.now())
+    }
+}
+
+impl DateFormat {
+    pub fn from_string(s: &str) -> Result<DateFormat, Error> {
+        match s {
+            "absolute" => Ok(DateFormat::Absolute),
+            "relative" => Ok(DateFormat::Relative),
+            "both" => Ok(DateFormat::Both),
+            _ => Err(Error::Usage(format!("invalid date format \"{}\"", s))),
+        }
+    }
+}
+
+impl DateStyle {
+    pub fn new() -> DateStyle {
+        DateStyle {
+            format: DateFormat::Absolute,
+            hours: Duration::hours(24),
+            limit: Duration::days(30),
+        }
+    }
+}
+
+pub fn set_date_style(style: DateStyle) {
+    DATE_STYLE.with(|x| x.set(style));
+}
+
+pub fn date_style() -> DateStyle {
+    DATE_STYLE.with(|x| x.get())
+}
+
+fn plural(n: i64, unit: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
 }
 
 impl Timestamp {
-    fn from_absolute_time(s: &String) -> Result<Timestamp, String> {
+    fn from_absolute_time(s: &str, tz: &TimeZone) -> Result<Timestamp, Error> {
         enum State { YEAR, MONTH, DAY, HOUR, MINUTE, SECOND, DONE }
         
         if s.is_empty() {
-            return Err(format!("empty input string"));
+            return Err(Error::Usage(format!("empty input string")));
         }
-            
-        let mut tm = time::now();
-        tm.tm_mon = 0;
-        tm.tm_mday = 1;
-        tm.tm_hour = 0;
-        tm.tm_min = 0;
-        tm.tm_sec = 0;
-        tm.tm_isdst = 1;
-        
-        let mut cur = 0;
+        
+        let mut fields = [0, 1, 1, 0, 0, 0];
+        
+        let mut cur = 0 as i64;
         let mut state = State::YEAR;
         
         for x in s.chars() {
-            if x.is_digit(10) {
-                cur = cur * 10 + x.to_digit(10).unwrap() as i32;
+            if let Some(digit) = x.to_digit(10) {
+                cur = try!(cur.checked_mul(10)
+                           .and_then(|y| y.checked_add(digit as i64))
+                           .ok_or(Error::Usage(format!("invalid date"))));
 
                 match state {
-                    State::YEAR => tm.tm_year = cur - 1900,
-                    State::MONTH => tm.tm_mon = cur - 1,
-                    State::DAY => tm.tm_mday = cur,
-                    State::HOUR => tm.tm_hour = cur,
-                    State::MINUTE => tm.tm_min = cur,
-                    State::SECOND => tm.tm_sec = cur,
+                    State::YEAR => fields[0] = cur,
+                    State::MONTH => fields[1] = cur,
+                    State::DAY => fields[2] = cur,
+                    State::HOUR => fields[3] = cur,
+                    State::MINUTE => fields[4] = cur,
+                    State::SECOND => fields[5] = cur,
                     State::DONE => break,
                 }
                 
@@ -80,102 +364,244 @@ impl Timestamp {
                 };
                 cur = 0;
             } else {
-                return Err(format!("invalid character {}", x));
+                return Err(Error::Usage(format!("invalid character {}", x)));
             }
         }
         
-        Ok(Timestamp::from_tm(&tm))
+        let (year, month, day) = (fields[0], fields[1], fields[2]);
+        let (hour, min, sec) = (fields[3], fields[4], fields[5]);
+        
+        if year > 9999 || month < 1 || month > 12 || day < 1 
+            || day > tz::days_in_month(year, month) {
+            return Err(Error::Usage(format!("invalid date")));
+        }
+        
+        if hour > 23 || min > 59 || sec > 60 {
+            return Err(Error::Usage(format!("invalid time of day")));
+        }
+        
+        let days = tz::days_from_civil(year, month, day);
+        let local = days * 86400 + hour * 3600 + min * 60 + sec;
+        
+        Ok(Timestamp::from_seconds(tz.to_utc(local)))
     }
     
-    fn from_relative_time(s: &String) -> Result<Timestamp, String> {
-        let mut num = 0 as i64;
-        let mut offset = 0 as i64;
+    fn from_relative_time(s: &str, clock: &Clock) 
+                          -> Result<Timestamp, Error> {
+        let now = clock.now();
+        let calendar = calendar::default();
         
-        for x in s.chars() {
-            if x.is_digit(10) {
-                num = num * 10 + x.to_digit(10).unwrap() as i64;
-            } else {
-                match x {
-                    'y' => offset += num * 3600 * 24 * 365,
-                    'm' => offset += num * 3600 * 24 * 30,
-                    'd' => offset += num * 3600 * 24,
-                    'h' => offset += num * 3600,
-                    's' => offset += num,
-                    _ => { 
-                        return Err(format!("invalid time specifier {}", x));
-                    }
-                }
-                num = 0;
-            }
+        match s {
+            "eod" => return Ok(calendar.end_of_day(&now)),
+            "eow" => return Ok(calendar.end_of_week(&now)),
+            _ => {},
         }
-    
-        Ok(Timestamp::now() + offset)
+        
+        let (offset, bdays) = try!(parse_relative(s));
+        
+        /* business days are counted one by one, so keep them in bounds */
+        if bdays > 100000 {
+            return Err(Error::Usage(format!("time \"{}\" is out of range", s)));
+        }
+        
+        let base = if bdays > 0 {
+            calendar.add_business_days(&now, bdays)
+        } else {
+            now
+        };
+        
+        base.seconds().checked_add(offset)
+            .map(|_| base + Duration::from_seconds(offset))
+            .ok_or(Error::Usage(format!("time \"{}\" is out of range", s)))
     }
     
     pub fn new() -> Timestamp {
-        Timestamp { seconds: i64::MAX }
+        Timestamp { seconds: i64::MAX, nanos: None }
     }
     
     pub fn from_seconds(seconds: i64) -> Timestamp {
-        Timestamp { seconds: seconds }
+        Timestamp { seconds: seconds, nanos: None }
     }
     
     pub fn from_timespec(ts: &time::Timespec) -> Timestamp {
-        Timestamp::from_seconds(ts.sec)
-    }
-    
-    pub fn from_tm(tm: &time::Tm) -> Timestamp {
-        Timestamp::from_timespec(&tm.to_timespec())
+        let nanos = if ts.nsec > 0 { Some(ts.nsec as u32) } else { None };
+        
+        Timestamp { seconds: ts.sec, nanos: nanos }
     }
     
-    pub fn from_string(s: &String) -> Result<Timestamp, String> {
+    pub fn from_string(s: &str, clock: &Clock) -> Result<Timestamp, Error> {
+        let s = s.trim();
+        
+        /* an absolute time may be followed by the name of a time zone */
+        if let Some(i) = s.rfind(' ') {
+            let name = &s[i + 1..];
+            
+            if name.chars().any(|x| x.is_alphabetic()) {
+                let tz = try!(TimeZone::from_name(name));
+                
+                return Timestamp::from_absolute_time(s[..i].trim(), &tz);
+            }
+        }
+        
         if s.chars().all(|x| !x.is_alphabetic()) {
-            Timestamp::from_absolute_time(s)
+            Timestamp::from_absolute_time(s, &tz::default())
         } else {
-            Timestamp::from_relative_time(s)
+            Timestamp::from_relative_time(s, clock)
         }
     }
     
-    pub fn now() -> Timestamp {
-        Timestamp::from_timespec(&time::get_time())
-    }
-    
     pub fn valid(&self) -> bool {
         self.seconds != i64::MAX
     }
     
-//     pub fn seconds(&self) -> i64 {
-//         self.seconds
-//     }
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+    
+    pub fn nanos(&self) -> u32 {
+        self.nanos.unwrap_or(0)
+    }
+    
+    pub fn millis(&self) -> u32 {
+        self.nanos() / 1000000
+    }
+    
+    fn key(&self) -> (i64, u32) {
+        (self.seconds, self.nanos())
+    }
+    
+    /* days since the epoch of the local date in the given zone */
+    pub fn day(&self, tz: &TimeZone) -> i64 {
+        let local = tz.to_local(self.seconds);
+        let day = local / 86400;
+        
+        if local % 86400 < 0 { day - 1 } else { day }
+    }
+    
+    /* seconds since local midnight in the given zone */
+    pub fn time_of_day(&self, tz: &TimeZone) -> i64 {
+        tz.to_local(self.seconds) - self.day(tz) * 86400
+    }
+    
+    /* the given number of seconds after local midnight of the day */
+    pub fn from_day(day: i64, secs: i64, tz: &TimeZone) -> Timestamp {
+        Timestamp::from_seconds(tz.to_utc(day * 86400 + secs))
+    }
+    
+    /* broken-down wall clock time of the given zone */
+    pub fn to_tm(&self, tz: &TimeZone) -> time::Tm {
+        let local = tz.to_local(self.seconds);
+        
+        time::at_utc(time::Timespec::new(local, 0))
+    }
+    
+    /* e.g. "2024-03-01T08:00:00.250Z", always in UTC */
+    pub fn to_iso8601(&self) -> String {
+        let tm = self.to_tm(&TimeZone::utc());
+        
+        format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", 1900 + tm.tm_year,
+                1 + tm.tm_mon, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec,
+                self.millis())
+    }
+    
+    /*
+     * Describes the distance to "now", e.g. "in 3h", "tomorrow" or 
+     * "2 days" followed by "past" if the timestamp lies in the past.
+     */
+    pub fn relative(&self, now: &Timestamp, style: &DateStyle, past: &str) 
+                    -> String {
+        if !self.valid() {
+            return format!("unspecified");
+        }
+        
+        let diff = (*self - *now).seconds;
+        let dist = diff.abs();
+        
+        let tz = tz::default();
+        let days = self.day(&tz) - now.day(&tz);
+        
+        let s = if dist < 60 {
+            return format!("now");
+        } else if dist < 3600 {
+            format!("{}min", dist / 60)
+        } else if dist < style.hours.seconds || days == 0 {
+            format!("{}h", dist / 3600)
+        } else if days == 1 {
+            return format!("tomorrow");
+        } else if days.abs() < 14 {
+            plural(days.abs(), "day")
+        } else if days.abs() < 60 {
+            plural(days.abs() / 7, "week")
+        } else {
+            plural(days.abs() / 30, "month")
+        };
+        
+        if diff > 0 {
+            format!("in {}", s)
+        } else {
+            format!("{} {}", s, past)
+        }
+    }
     
-    fn to_timespec(&self) -> time::Timespec {
-        time::Timespec::new(self.seconds, 0)
+    /* renders the timestamp according to the configured date style */
+    pub fn styled(&self, now: &Timestamp, past: &str) -> String {
+        let style = date_style();
+        
+        let relative = self.valid() 
+            && (*self - *now).abs() <= style.limit;
+        
+        match style.format {
+            DateFormat::Relative if relative => {
+                self.relative(now, &style, past)
+            },
+            DateFormat::Both if self.valid() => {
+                format!("{} ({})", self, self.relative(now, &style, past))
+            },
+            _ => format!("{}", self),
+        }
     }
 }
 
-impl Add<i64> for Timestamp {
+impl Add<Duration> for Timestamp {
     type Output = Timestamp;
     
-    fn add(mut self, sec: i64) -> Timestamp {
-        self.seconds += sec;
+    fn add(mut self, d: Duration) -> Timestamp {
+        self.seconds += d.seconds;
         self
     }
 }
 
-impl Sub<i64> for Timestamp {
+impl Sub<Duration> for Timestamp {
     type Output = Timestamp;
     
-    fn sub(mut self, sec: i64) -> Timestamp {
-        self.seconds -= sec;
+    fn sub(mut self, d: Duration) -> Timestamp {
+        self.seconds -= d.seconds;
         self
     }
 }
 
+impl Sub for Timestamp {
+    type Output = Duration;
+    
+    fn sub(self, other: Timestamp) -> Duration {
+        let mut secs = self.seconds - other.seconds;
+        
+        /* durations are truncated towards zero to whole seconds */
+        if secs > 0 && self.nanos() < other.nanos() {
+            secs -= 1;
+        } else if secs < 0 && self.nanos() > other.nanos() {
+            secs += 1;
+        }
+        
+        Duration::from_seconds(secs)
+    }
+}
+
 impl Ord for Timestamp {
     fn cmp(&self, other: &Timestamp) -> Ordering {
-        if self.seconds < other.seconds {
+        if self.key() < other.key() {
             Ordering::Less
-        } else if self.seconds > other.seconds {
+        } else if self.key() > other.key() {
             Ordering::Greater
         } else {
             Ordering::Equal
@@ -185,11 +611,11 @@ impl Ord for Timestamp {
 
 impl PartialEq for Timestamp {
     fn eq(&self, other: &Timestamp) -> bool {
-        self.seconds == other.seconds
+        self.key() == other.key()
     }
     
     fn ne(&self, other: &Timestamp) -> bool {
-        self.seconds != other.seconds
+        self.key() != other.key()
     }
 }
 
@@ -203,27 +629,26 @@ impl PartialOrd for Timestamp {
     }
     
     fn lt(&self, other: &Timestamp) -> bool {
-        self.seconds < other.seconds
+        self.key() < other.key()
     }
     
     fn le(&self, other: &Timestamp) -> bool {
-        self.seconds <= other.seconds
+        self.key() <= other.key()
     }
     
     fn gt(&self, other: &Timestamp) -> bool {
-        self.seconds > other.seconds
+        self.key() > other.key()
     }
     
     fn ge(&self, other: &Timestamp) -> bool {
-        self.seconds >= other.seconds
+        self.key() >= other.key()
     }
 }
 
 impl fmt::Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.valid() {
-            let ts = self.to_timespec();
-            let tm = time::at(ts);
+            let tm = self.to_tm(&tz::default());
             
             let year = 1900 + tm.tm_year;
             let month = 1 + tm.tm_mon;
@@ -239,3 +664,218 @@ impl fmt::Display for Timestamp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tz;
+    use tz::TimeZone;
+    use calendar;
+    use calendar::Calendar;
+
+    fn parse(s: &str, clock: &Clock) -> Timestamp {
+        tz::set_default(TimeZone::utc());
+        
+        Timestamp::from_string(s, clock).unwrap()
+    }
+    
+    fn at(seconds: i64) -> Timestamp {
+        Timestamp::from_seconds(seconds)
+    }
+    
+    #[test]
+    fn absolute_time() {
+        let clock = Clock::System;
+        
+        assert_eq!(parse("2024-03-01", &clock), at(1709251200));
+        assert_eq!(parse("2024-03-01 09:30", &clock), at(1709285400));
+        assert_eq!(parse("2024/03/01/09/30", &clock), at(1709285400));
+        assert_eq!(parse("2024-03-01 09:30:15", &clock), at(1709285415));
+    }
+    
+    #[test]
+    fn iso8601() {
+        let ts = Timestamp { seconds: 1709285415, nanos: Some(250000000) };
+        
+        assert_eq!(at(0).to_iso8601(), "1970-01-01T00:00:00.000Z");
+        assert_eq!(ts.to_iso8601(), "2024-03-01T09:30:15.250Z");
+    }
+    
+    #[test]
+    fn absolute_time_errors() {
+        let clock = Clock::System;
+        
+        assert!(Timestamp::from_string("", &clock).is_err());
+        assert!(Timestamp::from_string("2024-13-01", &clock).is_err());
+        assert!(Timestamp::from_string("2023-02-29", &clock).is_err());
+        assert!(Timestamp::from_string("2024-03-01 25:00", &clock).is_err());
+        assert!(Timestamp::from_string("2024.03.01", &clock).is_err());
+        assert!(Timestamp::from_string("99999999999999999999-01-01", &clock)
+                .is_err());
+        assert!(Timestamp::from_string("10000-01-01", &clock).is_err());
+    }
+    
+    #[test]
+    fn time_zones() {
+        let clock = Clock::System;
+        
+        assert_eq!(parse("2024-03-01 09:00 Europe/Berlin", &clock), 
+                   at(1709280000));
+        assert_eq!(parse("2024-07-01 09:00 Europe/Berlin", &clock), 
+                   at(1719817200));
+        assert_eq!(parse("2024-03-01 09:00 UTC", &clock), at(1709283600));
+        assert!(Timestamp::from_string("2024-03-01 Mars/Base", &clock)
+                .is_err());
+    }
+    
+    #[test]
+    fn daylight_saving_transitions() {
+        let clock = Clock::System;
+        
+        /* skipped wall clock times move past the gap */
+        assert_eq!(parse("2024-03-31 02:30 Europe/Berlin", &clock), 
+                   at(1711848600));
+        /* repeated wall clock times resolve to the earlier instant */
+        assert_eq!(parse("2024-10-27 02:30 Europe/Berlin", &clock), 
+                   at(1729989000));
+        /* beyond the transitions listed in the zone file */
+        assert_eq!(parse("2099-07-01 09:00 America/New_York", &clock), 
+                   at(4086594000));
+        assert_eq!(parse("2099-12-01 09:00 America/New_York", &clock), 
+                   at(4099816800));
+    }
+    
+    #[test]
+    fn relative_time() {
+        let clock = Clock::Fixed(at(1709251200));
+        
+        assert_eq!(parse("1d", &clock), at(1709251200 + 86400));
+        assert_eq!(parse("1d2h30s", &clock), at(1709251200 + 93630));
+        assert_eq!(parse("1y", &clock), at(1709251200 + 365 * 86400));
+        assert!(Timestamp::from_string("3x", &clock).is_err());
+        assert!(Timestamp::from_string("9999999999999999999d", &clock).is_err());
+        assert!(Timestamp::from_string("999999999999999w", &clock).is_err());
+        assert!(Timestamp::from_string("999999999bd", &clock).is_err());
+    }
+    
+    #[test]
+    fn business_days() {
+        let fri = Clock::Fixed(at(1709287200));
+        let fri_evening = Clock::Fixed(at(1709287200 + 8 * 3600));
+        
+        calendar::set_default(Calendar::new());
+        
+        assert_eq!(parse("eod", &fri), at(1709312400));
+        assert_eq!(parse("eod", &fri_evening), at(1709571600));
+        assert_eq!(parse("eow", &fri), at(1709312400));
+        assert_eq!(parse("eow", &fri_evening), at(1709917200));
+        assert_eq!(parse("3bd", &fri), at(1709744400));
+        assert_eq!(parse("1bd2h", &fri), at(1709571600 + 7200));
+        
+        let mut cal = Calendar::new();
+        cal.set_holidays("2024-03-05").unwrap();
+        cal.set_work_hours("08:00-16:30").unwrap();
+        calendar::set_default(cal);
+        
+        assert_eq!(parse("3bd", &fri), at(1709830800 - 1800));
+        
+        assert!(Duration::from_string("2bd").is_err());
+        assert!(Timestamp::from_string("3xd", &fri).is_err());
+    }
+    
+    #[test]
+    fn display() {
+        tz::set_default(TimeZone::utc());
+        
+        assert_eq!(format!("{}", at(1709285415)), "2024-03-01 :: 09:30:15");
+        assert_eq!(format!("{}", Timestamp::new()), "unspecified           ");
+        
+        tz::set_default(TimeZone::from_name("Europe/Berlin").unwrap());
+        
+        assert_eq!(format!("{}", at(1719817200)), "2024-07-01 :: 09:00:00");
+    }
+    
+    #[test]
+    fn relative_display() {
+        tz::set_default(TimeZone::utc());
+        
+        let now = at(1709280000);
+        let style = DateStyle::new();
+        
+        let rel = |x: i64| at(1709280000 + x).relative(&now, &style, "overdue");
+        
+        assert_eq!(rel(10), "now");
+        assert_eq!(rel(600), "in 10min");
+        assert_eq!(rel(3 * 3600), "in 3h");
+        assert_eq!(rel(-3 * 3600), "3h overdue");
+        assert_eq!(rel(26 * 3600), "tomorrow");
+        assert_eq!(rel(-2 * 86400), "2 days overdue");
+        assert_eq!(rel(21 * 86400), "in 3 weeks");
+        assert_eq!(Timestamp::new().relative(&now, &style, "ago"), 
+                   "unspecified");
+        
+        /* days before the epoch, 1969-12-31 22:00 and 1970-01-02 02:00 */
+        let now = at(-7200);
+        assert_eq!(at(93600).relative(&now, &style, "ago"), "in 2 days");
+        assert_eq!(now.relative(&at(93600), &style, "ago"), "2 days ago");
+    }
+    
+    #[test]
+    fn duration() {
+        let d = |s: &str| Duration::from_string(s).unwrap();
+        
+        assert_eq!(d("1d2h"), Duration::hours(26));
+        assert_eq!(d("90min"), Duration::minutes(90));
+        assert_eq!(d("2w"), Duration::days(14));
+        assert!(Duration::from_string("").is_err());
+        assert!(Duration::from_string("5").is_err());
+        assert!(Duration::from_string("99999999999999999999d").is_err());
+        assert!(Duration::from_string("9999999999999999y").is_err());
+        assert!(Duration::from_string("9223372036854775807s1s").is_err());
+        
+        assert_eq!(format!("{}", Duration::zero()), "0s");
+        assert_eq!(format!("{}", d("1d2h30min")), "1d 2h 30min");
+        assert_eq!(format!("{}", -d("90min")), "-1h 30min");
+        assert_eq!(format!("{:>6}", d("45s")), "   45s");
+        assert_eq!(format!("{:#}", d("1d2h30min")), "26h 30min");
+        
+        assert_eq!(d("1h") + d("30min"), Duration::minutes(90));
+        assert_eq!(d("1h") - d("30min"), Duration::minutes(30));
+        assert_eq!(d("1h") * 3, Duration::hours(3));
+        assert_eq!(vec![d("1h"), d("2h")].into_iter().sum::<Duration>(), 
+                   Duration::hours(3));
+    }
+    
+    #[test]
+    fn fixed_clock() {
+        let ts = at(1709251200);
+        
+        assert_eq!(Clock::Fixed(ts).now(), ts);
+        assert!(Clock::System.now() > ts);
+    }
+    
+    #[test]
+    fn sub_second_precision() {
+        let a = Timestamp::from_timespec(&time::Timespec::new(10, 250000000));
+        let b = Timestamp::from_timespec(&time::Timespec::new(10, 750000000));
+        
+        assert!(a < b);
+        assert!(at(10) < a);
+        assert_eq!(a.millis(), 250);
+        assert_eq!(b - a, Duration::zero());
+        assert_eq!(b - at(9), Duration::from_seconds(1));
+        assert_eq!(at(9) - b, Duration::from_seconds(-1));
+        assert_eq!(a + Duration::from_seconds(1) - a, Duration::from_seconds(1));
+    }
+    
+    #[test]
+    fn ordering() {
+        assert!(at(1) < at(2));
+        assert!(at(2) >= at(2));
+        assert!(at(1) < Timestamp::new());
+        assert!(!Timestamp::new().valid());
+        assert_eq!(at(10) + Duration::from_seconds(5), at(15));
+        assert_eq!(at(10) - Duration::from_seconds(5), at(5));
+        assert_eq!(at(10) - at(4), Duration::from_seconds(6));
+    }
+}
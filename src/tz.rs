@@ -0,0 +1,734 @@
+/*
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2016 Steffen Nuessle
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ */
+
+use std::fs::File;
+use std::io::Read;
+use std::env;
+use std::path::PathBuf;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+static ZONEINFO_DIR: &'static str = "/usr/share/zoneinfo";
+
+#[derive(Clone, Copy, Debug)]
+enum RuleDay {
+    /* Jn: 1 <= n <= 365, February 29th is never counted */
+    Julian(i64),
+    /* n: 0 <= n <= 365, February 29th is counted in leap years */
+    Zero(i64),
+    /* Mm.w.d: day d of week w of month m */
+    Month(i64, i64, i64),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    std_offset: i64,
+    dst_offset: i64,
+    start: RuleDay,
+    start_time: i64,
+    end: RuleDay,
+    end_time: i64,
+}
+
+#[derive(Clone, Debug)]
+struct PosixTz {
+    std_offset: i64,
+    rule: Option<Rule>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TimeZone {
+    transitions: Vec<i64>,
+    indices: Vec<usize>,
+    offsets: Vec<i64>,
+    footer: Option<PosixTz>,
+}
+
+thread_local!(static DEFAULT_ZONE: RefCell<Option<Rc<TimeZone>>> =
+    RefCell::new(None));
+
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 };
+
+    (year, month, day)
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/* 0 = Sunday, 1 = Monday, ..., 6 = Saturday */
+pub fn weekday(days: i64) -> i64 {
+    let wd = (days + 4) % 7;
+
+    if wd < 0 { wd + 7 } else { wd }
+}
+
+fn floor_div(a: i64, b: i64) -> i64 {
+    let q = a / b;
+
+    if (a % b != 0) && ((a < 0) != (b < 0)) { q - 1 } else { q }
+}
+
+impl RuleDay {
+    fn days(&self, year: i64) -> i64 {
+        let jan1 = days_from_civil(year, 1, 1);
+
+        match *self {
+            RuleDay::Julian(n) => {
+                if is_leap_year(year) && n >= 60 {
+                    jan1 + n
+                } else {
+                    jan1 + n - 1
+                }
+            },
+            RuleDay::Zero(n) => jan1 + n,
+            RuleDay::Month(m, w, d) => {
+                let first = days_from_civil(year, m, 1);
+                let mut day = (d - weekday(first) + 7) % 7 + (w - 1) * 7;
+
+                while day >= days_in_month(year, m) {
+                    day -= 7;
+                }
+
+                first + day
+            },
+        }
+    }
+}
+
+impl Rule {
+    fn offset_at(&self, t: i64) -> i64 {
+        let (year, _, _) = civil_from_days(floor_div(t + self.std_offset, 86400));
+
+        let start = self.start.days(year) * 86400 + self.start_time
+                  - self.std_offset;
+        let end = self.end.days(year) * 86400 + self.end_time
+                - self.dst_offset;
+
+        let dst = if start < end {
+            t >= start && t < end
+        } else {
+            !(t >= end && t < start)
+        };
+
+        if dst { self.dst_offset } else { self.std_offset }
+    }
+}
+
+struct PosixParser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl<'a> PosixParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.i).cloned()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn name(&mut self) -> Result<(), String> {
+        let begin = self.i;
+
+        if self.eat(b'<') {
+            while self.peek().map_or(false, |x| x != b'>') {
+                self.i += 1;
+            }
+
+            if !self.eat(b'>') {
+                return Err(format!("unterminated time zone name"));
+            }
+        } else {
+            while self.peek().map_or(false, |x| (x as char).is_alphabetic()) {
+                self.i += 1;
+            }
+
+            if self.i - begin < 3 {
+                return Err(format!("invalid time zone name"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<i64, String> {
+        let mut num = 0 as i64;
+        let begin = self.i;
+
+        while let Some(x) = self.peek() {
+            if x < b'0' || x > b'9' {
+                break;
+            }
+
+            num = try!(num.checked_mul(10)
+                       .and_then(|y| y.checked_add((x - b'0') as i64))
+                       .ok_or(format!("number out of range")));
+            self.i += 1;
+        }
+
+        if self.i == begin {
+            return Err(format!("expected a number"));
+        }
+
+        Ok(num)
+    }
+
+    /* [+-]hh[:mm[:ss]] */
+    fn time(&mut self) -> Result<i64, String> {
+        let sign = if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        };
+
+        let mut secs = try!(self.number()) * 3600;
+
+        if self.eat(b':') {
+            secs += try!(self.number()) * 60;
+
+            if self.eat(b':') {
+                secs += try!(self.number());
+            }
+        }
+
+        Ok(sign * secs)
+    }
+
+    fn rule_day(&mut self) -> Result<(RuleDay, i64), String> {
+        let day = if self.eat(b'J') {
+            RuleDay::Julian(try!(self.number()))
+        } else if self.eat(b'M') {
+            let m = try!(self.number());
+
+            if !self.eat(b'.') {
+                return Err(format!("invalid month rule"));
+            }
+
+            let w = try!(self.number());
+
+            if !self.eat(b'.') {
+                return Err(format!("invalid month rule"));
+            }
+
+            let d = try!(self.number());
+
+            if m < 1 || m > 12 || w < 1 || w > 5 || d > 6 {
+                return Err(format!("invalid month rule"));
+            }
+
+            RuleDay::Month(m, w, d)
+        } else {
+            RuleDay::Zero(try!(self.number()))
+        };
+
+        let time = if self.eat(b'/') { try!(self.time()) } else { 7200 };
+
+        Ok((day, time))
+    }
+
+    fn parse(&mut self) -> Result<PosixTz, String> {
+        try!(self.name());
+
+        /* POSIX offsets count westwards */
+        let std_offset = -try!(self.time());
+
+        if self.peek().is_none() {
+            return Ok(PosixTz { std_offset: std_offset, rule: None });
+        }
+
+        try!(self.name());
+
+        let dst_offset = match self.peek() {
+            Some(b',') | None => std_offset + 3600,
+            _ => -try!(self.time()),
+        };
+
+        let (start, start_time, end, end_time) = if self.eat(b',') {
+            let (start, start_time) = try!(self.rule_day());
+
+            if !self.eat(b',') {
+                return Err(format!("missing end of daylight saving time"));
+            }
+
+            let (end, end_time) = try!(self.rule_day());
+
+            (start, start_time, end, end_time)
+        } else {
+            (RuleDay::Month(3, 2, 0), 7200, RuleDay::Month(11, 1, 0), 7200)
+        };
+
+        if self.peek().is_some() {
+            return Err(format!("trailing characters"));
+        }
+
+        let rule = Rule {
+            std_offset: std_offset,
+            dst_offset: dst_offset,
+            start: start,
+            start_time: start_time,
+            end: end,
+            end_time: end_time,
+        };
+
+        Ok(PosixTz { std_offset: std_offset, rule: Some(rule) })
+    }
+}
+
+impl PosixTz {
+    fn parse(s: &str) -> Result<PosixTz, String> {
+        let mut parser = PosixParser { s: s.as_bytes(), i: 0 };
+
+        parser.parse()
+    }
+
+    fn offset_at(&self, t: i64) -> i64 {
+        match self.rule {
+            Some(ref rule) => rule.offset_at(t),
+            None => self.std_offset,
+        }
+    }
+}
+
+fn read_be(data: &[u8], pos: usize, size: usize) -> Result<i64, String> {
+    if pos.checked_add(size).map_or(true, |x| x > data.len()) {
+        return Err(format!("unexpected end of file"));
+    }
+
+    let mut val = 0u64;
+
+    for x in &data[pos..pos + size] {
+        val = (val << 8) | *x as u64;
+    }
+
+    /* sign-extend 32 bit values */
+    if size == 4 {
+        Ok(val as u32 as i32 as i64)
+    } else {
+        Ok(val as i64)
+    }
+}
+
+impl TimeZone {
+    pub fn utc() -> TimeZone {
+        TimeZone {
+            transitions: vec![],
+            indices: vec![],
+            offsets: vec![0],
+            footer: None,
+        }
+    }
+
+    pub fn local() -> TimeZone {
+        let tz = env::var("TZ").ok().and_then(|x| TimeZone::from_tz(&x));
+
+        if let Some(tz) = tz {
+            return tz;
+        }
+
+        TimeZone::from_file("localtime", &PathBuf::from("/etc/localtime"))
+            .unwrap_or(TimeZone::utc())
+    }
+
+    /* 
+     * A value of "TZ": a zone name, an absolute path to a time zone file or
+     * a POSIX rule, e.g. "CET-1CEST,M3.5.0,M10.5.0/3".
+     */
+    fn from_tz(val: &str) -> Option<TimeZone> {
+        let name = val.trim_left_matches(':');
+
+        if name.starts_with('/') {
+            return TimeZone::from_file(name, &PathBuf::from(name)).ok();
+        }
+
+        /* "local" would lead right back here */
+        if name != "local" {
+            if let Ok(tz) = TimeZone::from_name(name) {
+                return Some(tz);
+            }
+        }
+
+        PosixTz::parse(name).ok().map(|footer| {
+            let mut tz = TimeZone::utc();
+            tz.offsets = vec![];
+            tz.footer = Some(footer);
+
+            tz
+        })
+    }
+
+    pub fn from_name(name: &str) -> Result<TimeZone, String> {
+        match name {
+            "UTC" | "utc" | "Z" | "GMT" => return Ok(TimeZone::utc()),
+            "local" => return Ok(TimeZone::local()),
+            _ => {},
+        }
+
+        let valid = !name.is_empty() && !name.starts_with('/')
+            && name.split('/').all(|x| !x.is_empty() && x != "..");
+
+        if !valid {
+            return Err(format!("invalid time zone \"{}\"", name));
+        }
+
+        let mut path = match env::var("TZDIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => PathBuf::from(ZONEINFO_DIR),
+        };
+        path.push(name);
+
+        TimeZone::from_file(name, &path)
+    }
+
+    fn from_file(name: &str, path: &PathBuf) -> Result<TimeZone, String> {
+        let mut data = vec![];
+
+        let result = File::open(path).and_then(|mut x| x.read_to_end(&mut data));
+        if result.is_err() {
+            return Err(format!("unknown time zone \"{}\"", name));
+        }
+
+        TimeZone::from_tzif(&data)
+            .map_err(|x| format!("invalid time zone file for \"{}\" - {}",
+                                 name, x))
+    }
+
+    fn from_tzif(data: &[u8]) -> Result<TimeZone, String> {
+        if data.len() < 44 || &data[0..4] != b"TZif" {
+            return Err(format!("bad magic"));
+        }
+
+        let mut tz = TimeZone {
+            transitions: vec![],
+            indices: vec![],
+            offsets: vec![],
+            footer: None,
+        };
+
+        let mut pos = try!(tz.read_block(data, 0, 4));
+
+        /* version 2+ files repeat the data with 64 bit times and a footer */
+        if data[4] >= b'2' {
+            pos = try!(tz.read_block(data, pos, 8));
+
+            if pos < data.len() && data[pos] == b'\n' {
+                let end = data[pos + 1..].iter().position(|&x| x == b'\n');
+
+                if let Some(end) = end {
+                    let s = String::from_utf8_lossy(&data[pos + 1..pos + 1 + end]);
+
+                    if !s.is_empty() {
+                        tz.footer = Some(try!(PosixTz::parse(&s)));
+                    }
+                }
+            }
+        }
+
+        if tz.offsets.is_empty() {
+            return Err(format!("no local time types"));
+        }
+
+        Ok(tz)
+    }
+
+    fn read_block(&mut self, data: &[u8], begin: usize, size: usize)
+                  -> Result<usize, String> {
+        let mut counts = [0usize; 6];
+
+        if data.len() < begin + 44 {
+            return Err(format!("unexpected end of file"));
+        }
+
+        for i in 0..6 {
+            counts[i] = try!(read_be(data, begin + 20 + i * 4, 4)) as usize;
+        }
+
+        let (isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt) =
+            (counts[0], counts[1], counts[2], counts[3], counts[4], counts[5]);
+
+        let mut pos = begin + 44;
+
+        self.transitions.clear();
+        self.indices.clear();
+        self.offsets.clear();
+
+        for _ in 0..timecnt {
+            self.transitions.push(try!(read_be(data, pos, size)));
+            pos += size;
+        }
+
+        for _ in 0..timecnt {
+            let index = try!(read_be(data, pos, 1)) as usize;
+
+            if index >= typecnt {
+                return Err(format!("invalid local time type"));
+            }
+
+            self.indices.push(index);
+            pos += 1;
+        }
+
+        for _ in 0..typecnt {
+            self.offsets.push(try!(read_be(data, pos, 4)));
+            pos += 6;
+        }
+
+        /* the counts come from the file and may be anything */
+        let end = leapcnt.checked_mul(size + 4)
+            .and_then(|x| x.checked_add(charcnt))
+            .and_then(|x| x.checked_add(isstdcnt))
+            .and_then(|x| x.checked_add(isutcnt))
+            .and_then(|x| x.checked_add(pos));
+
+        match end {
+            Some(x) if x <= data.len() => Ok(x),
+            _ => Err(format!("unexpected end of file")),
+        }
+    }
+
+    /* UTC offset in seconds at the given point in time */
+    pub fn offset_at(&self, t: i64) -> i64 {
+        let n = match self.transitions.binary_search(&t) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+
+        if n == self.transitions.len() {
+            if let Some(ref footer) = self.footer {
+                return footer.offset_at(t);
+            }
+        }
+
+        if n == 0 {
+            self.offsets[0]
+        } else {
+            self.offsets[self.indices[n - 1]]
+        }
+    }
+
+    /*
+     * Converts seconds of wall clock time into seconds since the epoch.
+     * Ambiguous times resolve to the earlier instant and times skipped
+     * by a transition are moved past the gap.
+     */
+    pub fn to_utc(&self, local: i64) -> i64 {
+        let before = self.offset_at(local - 86400);
+        let after = self.offset_at(local + 86400);
+
+        let a = local - before;
+        let b = local - after;
+
+        match (self.offset_at(a) == before, self.offset_at(b) == after) {
+            (true, true) => if a < b { a } else { b },
+            (false, true) => b,
+            _ => a,
+        }
+    }
+
+    /* Converts seconds since the epoch into seconds of wall clock time */
+    pub fn to_local(&self, t: i64) -> i64 {
+        t + self.offset_at(t)
+    }
+}
+
+pub fn set_default(tz: TimeZone) {
+    DEFAULT_ZONE.with(|x| *x.borrow_mut() = Some(Rc::new(tz)));
+}
+
+pub fn default() -> Rc<TimeZone> {
+    DEFAULT_ZONE.with(|x| {
+        let mut zone = x.borrow_mut();
+
+        if zone.is_none() {
+            *zone = Some(Rc::new(TimeZone::local()));
+        }
+
+        zone.as_ref().unwrap().clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn at(year: i64, month: i64, day: i64, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * 86400 + hour * 3600
+    }
+
+    fn be(v: &mut Vec<u8>, x: i64, size: usize) {
+        for i in (0..size).rev() {
+            v.push((x >> (i * 8)) as u8);
+        }
+    }
+
+    /* a zone switching from UTC to CET+1 at 1000, "leapcnt" leap seconds */
+    fn block(v: &mut Vec<u8>, size: usize, leapcnt: i64) {
+        v.extend(b"TZif2");
+        v.extend(&[0; 15]);
+
+        for &x in &[0, 0, leapcnt, 1, 2, 8] {
+            be(v, x, 4);
+        }
+
+        be(v, 1000, size);
+        v.push(1);
+
+        for &(offset, dst, index) in &[(0, 0, 0), (3600, 1, 4)] {
+            be(v, offset, 4);
+            v.push(dst);
+            v.push(index);
+        }
+
+        v.extend(b"UTC\0CET\0");
+    }
+
+    fn tzif() -> Vec<u8> {
+        let mut v = vec![];
+        block(&mut v, 4, 0);
+        block(&mut v, 8, 0);
+        v.extend(b"\nCET-1CEST,M3.5.0,M10.5.0/3\n".iter());
+
+        v
+    }
+
+    #[test]
+    fn posix_rules() {
+        /* the daylight saving time spans the turn of the year */
+        let tz = PosixTz::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        let end = at(2024, 4, 6, 16);
+
+        assert_eq!(tz.offset_at(at(2024, 1, 15, 0)), 11 * 3600);
+        assert_eq!(tz.offset_at(at(2024, 7, 15, 0)), 10 * 3600);
+        assert_eq!(tz.offset_at(end - 1), 11 * 3600);
+        assert_eq!(tz.offset_at(end), 10 * 3600);
+
+        let tz = PosixTz::parse("<+03>-3").unwrap();
+        assert_eq!(tz.offset_at(0), 3 * 3600);
+
+        assert!(PosixTz::parse("EST5EDT,J60,J300").is_ok());
+        assert!(PosixTz::parse("EST5EDT,M3.6.0,M11.1.0").is_err());
+        assert!(PosixTz::parse("EST5EDT,M3.2").is_err());
+        assert!(PosixTz::parse("EST99999999999999999999").is_err());
+        assert!(PosixTz::parse("E5").is_err());
+    }
+
+    #[test]
+    fn rule_days() {
+        /* Jn never counts February 29th, n does */
+        assert_eq!(RuleDay::Julian(60).days(2024), days_from_civil(2024, 3, 1));
+        assert_eq!(RuleDay::Julian(60).days(2023), days_from_civil(2023, 3, 1));
+        assert_eq!(RuleDay::Zero(59).days(2024), days_from_civil(2024, 2, 29));
+        assert_eq!(RuleDay::Zero(59).days(2023), days_from_civil(2023, 3, 1));
+
+        /* week 5 is the last week of the month */
+        let last_sunday = RuleDay::Month(3, 5, 0);
+        assert_eq!(last_sunday.days(2024), days_from_civil(2024, 3, 31));
+        assert_eq!(last_sunday.days(2023), days_from_civil(2023, 3, 26));
+
+        let last_thursday = RuleDay::Month(2, 5, 4);
+        assert_eq!(last_thursday.days(2024), days_from_civil(2024, 2, 29));
+        assert_eq!(last_thursday.days(2023), days_from_civil(2023, 2, 23));
+    }
+
+    #[test]
+    fn tzif_files() {
+        let tz = TimeZone::from_tzif(&tzif()).unwrap();
+
+        assert_eq!(tz.offset_at(999), 0);
+        assert_eq!(tz.offset_at(1000), 3600);
+        assert_eq!(tz.offset_at(at(2024, 1, 15, 0)), 3600);
+        assert_eq!(tz.offset_at(at(2024, 7, 15, 0)), 7200);
+        assert_eq!(tz.to_utc(at(2024, 7, 15, 2)), at(2024, 7, 15, 0));
+
+        let data = tzif();
+
+        assert!(TimeZone::from_tzif(&data[..30]).is_err());
+        assert!(TimeZone::from_tzif(&data[..60]).is_err());
+        assert!(TimeZone::from_tzif(b"not a time zone file").is_err());
+
+        let mut empty = b"TZif".to_vec();
+        empty.extend(&[0; 40]);
+        assert!(TimeZone::from_tzif(&empty).is_err());
+
+        /* counts that overflow the position in the file */
+        let mut huge = vec![];
+        block(&mut huge, 4, 0xffffffff);
+        assert!(TimeZone::from_tzif(&huge).is_err());
+    }
+
+    #[test]
+    fn tz_values() {
+        let mut path = ::std::env::temp_dir();
+        path.push(format!("tap-test-{}.tzif", ::std::process::id()));
+        fs::write(&path, tzif()).unwrap();
+
+        let tz = TimeZone::from_tz(&path.to_string_lossy()).unwrap();
+        assert_eq!(tz.offset_at(at(2024, 7, 15, 0)), 7200);
+
+        fs::remove_file(&path).unwrap();
+
+        let tz = TimeZone::from_tz("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(tz.offset_at(at(2024, 1, 15, 0)), 3600);
+
+        assert_eq!(TimeZone::from_tz("UTC").unwrap().offset_at(0), 0);
+        assert!(TimeZone::from_tz("local").is_none());
+        assert!(TimeZone::from_tz("/nonexistent/zone").is_none());
+    }
+}